@@ -0,0 +1,69 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::path::Path;
+
+use dhall::{Ctxt, Parsed};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/benches/fixtures");
+
+/// Resolve, typecheck and normalize an expression, then convert it back to an `Expr` so that the
+/// whole normal form gets computed.
+fn normalize(parsed: Parsed) {
+    Ctxt::with_new(|cx| {
+        let resolved = parsed.resolve(cx).unwrap();
+        let normalized = resolved.typecheck(cx).unwrap().normalize(cx);
+        criterion::black_box(normalized.to_expr(cx));
+    })
+}
+
+/// A chain of `n` recursive record merges, each touching a shared nested record.
+fn record_merge_chain(n: usize) -> String {
+    (0..n)
+        .map(|i| {
+            format!(
+                "{{ field{i} = {i}, shared = {{ inner{i} = \"{i}\" }} }}",
+                i = i
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" ∧ ")
+}
+
+/// A record with `n` fields that all use the same import.
+fn shared_import(n: usize) -> String {
+    let import = Path::new(FIXTURES).join("kubernetes/package.dhall");
+    let fields = (0..n)
+        .map(|i| format!("field{} = {}", i, import.display()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("{{ {} }}", fields)
+}
+
+fn bench_normalize(c: &mut Criterion) {
+    let natural_fold =
+        Parsed::parse_file(&Path::new(FIXTURES).join("natural-fold.dhall"))
+            .unwrap();
+    c.bench_function("natural fold", |b| {
+        b.iter(|| normalize(natural_fold.clone()))
+    });
+
+    let merge_chain = Parsed::parse_str(&record_merge_chain(200)).unwrap();
+    c.bench_function("record merge chain", |b| {
+        b.iter(|| normalize(merge_chain.clone()))
+    });
+
+    let shared = Parsed::parse_str(&shared_import(100)).unwrap();
+    c.bench_function("shared import", |b| b.iter(|| normalize(shared.clone())));
+
+    // Reads the imported files on each iteration, like a fresh run would.
+    let kubernetes = Path::new(FIXTURES).join("kubernetes/deployments.dhall");
+    c.bench_function("kubernetes deployments", |b| {
+        b.iter(|| normalize(Parsed::parse_file(&kubernetes).unwrap()))
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = bench_normalize
+}
+criterion_main!(benches);
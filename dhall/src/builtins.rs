@@ -1,17 +1,24 @@
+use num_traits::{One, Signed, ToPrimitive, Zero};
 use std::collections::{BTreeMap, HashMap};
 use std::convert::TryInto;
+use std::rc::Rc;
 
+use crate::error::Error;
 use crate::operations::{BinOp, OpKind};
-use crate::semantics::{nze, Hir, HirKind, Nir, NirKind, NzEnv, VarEnv};
+use crate::semantics::{
+    mkerr, nze, typecheck, ConvMemo, Hir, HirKind, Nir, NirKind, NzEnv, TyEnv,
+    VarEnv,
+};
 use crate::syntax::Const::Type;
 use crate::syntax::{
     Const, Expr, ExprKind, InterpolatedText, InterpolatedTextContents, Label,
     NaiveDouble, NumKind, Span, UnspannedExpr, V,
 };
-use crate::{Ctxt, Parsed};
+use crate::{Ctxt, Parsed, ToExprOptions};
 
 /// Built-ins
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Builtin {
     Bool,
     Natural,
@@ -82,6 +89,25 @@ impl Builtin {
             _ => None,
         }
     }
+
+    /// The number of arguments the builtin takes before it can be reduced.
+    pub fn arity(self) -> usize {
+        use Builtin::*;
+        match self {
+            Bool | Natural | Integer | Double | Text => 0,
+            List | Optional | OptionalNone => 1,
+            NaturalBuild | NaturalIsZero | NaturalEven | NaturalOdd
+            | NaturalToInteger | NaturalShow => 1,
+            NaturalSubtract => 2,
+            NaturalFold => 4,
+            IntegerToDouble | IntegerShow | IntegerNegate | IntegerClamp => 1,
+            DoubleShow | TextShow => 1,
+            TextReplace => 3,
+            ListBuild | ListLength | ListHead | ListLast | ListIndexed
+            | ListReverse => 2,
+            ListFold => 5,
+        }
+    }
 }
 
 /// A partially applied builtin.
@@ -116,6 +142,218 @@ impl<'cx> BuiltinClosure<'cx> {
     }
 }
 
+/// Primitives provided by the embedder rather than by the standard, e.g. `MyApp/uuid : Text`.
+/// Each has a type, used by the typechecker, and a reduction rule, used by the normalizer. They
+/// are available to all the expressions typechecked and normalized in a context created with
+/// [`Ctxt::with_custom_builtins`].
+///
+/// Names must be of the form `Namespace/name`, where `Namespace` isn't the namespace of any
+/// standard builtin like `Natural` or `List`, so they can't clash with current or future standard
+/// builtins. Since `/` is not allowed in plain identifiers, Dhall code refers to them between
+/// backticks, e.g. `` `MyApp/uuid` ``. They behave like variables bound outside the expression, so
+/// a binder with the same name shadows them.
+#[derive(Clone, Default)]
+pub struct CustomBuiltins(HashMap<Label, CustomBuiltin>);
+
+#[derive(Clone)]
+struct CustomBuiltin {
+    ty: Expr,
+    arity: usize,
+    reduce: Rc<dyn Fn(&[Expr]) -> Option<Expr>>,
+}
+
+/// Namespaces of the standard builtins, including those we don't support yet.
+const RESERVED_NAMESPACES: &[&str] = &[
+    "Bool", "Natural", "Integer", "Double", "Text", "List", "Optional", "Date",
+    "Time", "TimeZone", "Bytes",
+];
+
+impl CustomBuiltins {
+    pub fn new() -> Self {
+        CustomBuiltins::default()
+    }
+
+    /// Register the builtin `name`, of type `ty`. `ty` must be a closed Dhall type. Once the
+    /// builtin is applied to `arity` arguments, the normalizer calls `reduce` with the normal
+    /// forms of the arguments. It returns the result, or `None` to leave the application as it
+    /// is. The result must have the type that `ty` promises; if it doesn't typecheck, the
+    /// application is left as it is too.
+    ///
+    /// `reduce` is only called when all the arguments are literals: numbers, `Text` without
+    /// interpolations, simple types, and records, unions, lists and optionals of those. Other
+    /// arguments, like functions or variables, leave the application as it is.
+    ///
+    /// Fails if `name` is not of the form `Namespace/name` described above, or if `ty` is not a
+    /// closed type.
+    pub fn insert(
+        &mut self,
+        name: &str,
+        ty: Expr,
+        arity: usize,
+        reduce: impl Fn(&[Expr]) -> Option<Expr> + 'static,
+    ) -> Result<(), Error> {
+        match name.split_once('/') {
+            Some((namespace, rest))
+                if !namespace.is_empty()
+                    && !rest.is_empty()
+                    && !RESERVED_NAMESPACES.contains(&namespace) => {}
+            _ => mkerr(format!(
+                "custom builtin `{}` must be named `Namespace/name`, outside of the namespaces of standard builtins",
+                name
+            ))?,
+        }
+        if let Err(err) = Ctxt::with_new(|cx| eval_type(cx, &ty).map(|_| ())) {
+            mkerr(format!(
+                "invalid type for custom builtin `{}`: {}",
+                name, err
+            ))?
+        }
+        let builtin = CustomBuiltin {
+            ty,
+            arity,
+            reduce: Rc::new(reduce),
+        };
+        self.0.insert(name.into(), builtin);
+        Ok(())
+    }
+
+    pub(crate) fn contains(&self, name: &Label) -> bool {
+        self.0.contains_key(name)
+    }
+
+    /// The types of the builtins, in the context `cx`.
+    pub(crate) fn types<'cx>(
+        &self,
+        cx: Ctxt<'cx>,
+    ) -> HashMap<Label, crate::semantics::Type<'cx>> {
+        self.0
+            .iter()
+            .map(|(name, builtin)| {
+                let ty = eval_type(cx, &builtin.ty)
+                    .expect("custom builtin types are checked on insertion");
+                (name.clone(), ty)
+            })
+            .collect()
+    }
+}
+
+/// Typecheck and evaluate a closed type.
+fn eval_type<'cx>(
+    cx: Ctxt<'cx>,
+    ty: &Expr,
+) -> Result<crate::semantics::Type<'cx>, Error> {
+    let hir = Parsed::from_expr_without_imports(ty.clone())
+        .skip_resolve(cx)?
+        .0;
+    Ok(typecheck(cx, &hir)?.eval_to_type(&TyEnv::new(cx))?)
+}
+
+/// A custom builtin applied to too few arguments, or that did not reduce.
+/// Invariant: the evaluation of the given args must not be able to progress further
+#[derive(Debug, Clone)]
+pub struct CustomBuiltinClosure<'cx> {
+    env: NzEnv<'cx>,
+    name: Label,
+    /// Arguments applied to the closure so far.
+    args: Vec<Nir<'cx>>,
+}
+
+impl<'cx> CustomBuiltinClosure<'cx> {
+    pub fn new(name: Label, env: NzEnv<'cx>) -> NirKind<'cx> {
+        apply_custom_builtin(name, Vec::new(), env)
+    }
+    pub fn apply(&self, a: Nir<'cx>) -> NirKind<'cx> {
+        use std::iter::once;
+        let args = self.args.iter().cloned().chain(once(a)).collect();
+        apply_custom_builtin(self.name.clone(), args, self.env.clone())
+    }
+    pub fn to_hirkind(&self, venv: VarEnv) -> HirKind<'cx> {
+        let head = HirKind::CustomBuiltin(self.name.clone());
+        self.args.iter().fold(head, |acc, v| {
+            HirKind::Expr(ExprKind::Op(OpKind::App(
+                Hir::new(acc, Span::Artificial),
+                v.to_hir(venv),
+            )))
+        })
+    }
+}
+
+/// Whether the value is made only of literals and simple types. In particular, it has no free
+/// variables, so it can be converted to an `Expr` on its own.
+fn is_literal(v: &Nir<'_>) -> bool {
+    use NirKind::*;
+    match v.kind() {
+        NirKind::Const(_) | Num(_) | BuiltinType(_) => true,
+        TextLit(t) => t.as_text().is_some(),
+        EmptyOptionalLit(x) | NEOptionalLit(x) | OptionalType(x)
+        | EmptyListLit(x) | ListType(x) => is_literal(x),
+        NEListLit(xs) => xs.iter().all(is_literal),
+        RecordLit(kvs) | RecordType(kvs) => kvs.values().all(is_literal),
+        UnionType(kts) | UnionConstructor(_, kts) => {
+            kts.values().flatten().all(is_literal)
+        }
+        UnionLit(_, x, kts) => {
+            is_literal(x) && kts.values().flatten().all(is_literal)
+        }
+        _ => false,
+    }
+}
+
+fn apply_custom_builtin<'cx>(
+    name: Label,
+    args: Vec<Nir<'cx>>,
+    env: NzEnv<'cx>,
+) -> NirKind<'cx> {
+    let cx = env.cx();
+    let builtin = &cx.custom_builtins().0[&name];
+    // Once the builtin is stuck, more arguments won't help.
+    let reduced = if args.len() == builtin.arity
+        && env.options().reduce_builtins
+        && args.iter().all(is_literal)
+    {
+        let args: Vec<Expr> = args
+            .iter()
+            .map(|v| v.to_expr(cx, ToExprOptions::default()))
+            .collect();
+        (builtin.reduce)(&args)
+    } else {
+        None
+    };
+    // The result comes from outside, so we make sure it is valid before using it.
+    let reduced = reduced.and_then(|e| {
+        let resolved = Parsed::from_expr_without_imports(e).skip_resolve(cx);
+        resolved.ok()?.typecheck(cx).ok()
+    });
+    match reduced {
+        Some(typed) => typed.hir.eval_closed_expr(cx).kind().clone(),
+        None => NirKind::AppliedCustomBuiltin(CustomBuiltinClosure {
+            env,
+            name,
+            args,
+        }),
+    }
+}
+
+impl<'cx> CustomBuiltinClosure<'cx> {
+    pub(crate) fn conv(&self, other: &Self, memo: &mut ConvMemo<'cx>) -> bool {
+        self.name == other.name && conv_args(&self.args, &other.args, memo)
+    }
+}
+impl<'cx> std::cmp::PartialEq for CustomBuiltinClosure<'cx> {
+    fn eq(&self, other: &Self) -> bool {
+        self.conv(other, &mut ConvMemo::default())
+    }
+}
+
+fn conv_args<'cx>(
+    xs: &[Nir<'cx>],
+    ys: &[Nir<'cx>],
+    memo: &mut ConvMemo<'cx>,
+) -> bool {
+    xs.len() == ys.len() && xs.iter().zip(ys).all(|(x, y)| x.conv(y, memo))
+}
+impl<'cx> std::cmp::Eq for CustomBuiltinClosure<'cx> {}
+
 pub fn rc(x: UnspannedExpr) -> Expr {
     Expr::new(x, Span::Artificial)
 }
@@ -293,7 +531,7 @@ macro_rules! make_closure {
         rc(ExprKind::Op(OpKind::BinOp(
             BinOp::NaturalPlus,
             make_closure!($($v)*),
-            rc(ExprKind::Num(NumKind::Natural(1)))
+            rc(ExprKind::Num(NumKind::Natural(One::one())))
         )))
     };
     ([ $($head:tt)* ] # $($tail:tt)*) => {{
@@ -333,93 +571,162 @@ fn apply_builtin<'cx>(
             .eval(env.clone())
     };
 
-    let ret = match (b, args.as_slice()) {
-        (Builtin::Bool, [])
-        | (Builtin::Natural, [])
-        | (Builtin::Integer, [])
-        | (Builtin::Double, [])
-        | (Builtin::Text, []) => Ret::NirKind(BuiltinType(b)),
-        (Builtin::Optional, [t]) => Ret::NirKind(OptionalType(t.clone())),
-        (Builtin::List, [t]) => Ret::NirKind(ListType(t.clone())),
+    // A builtin reduces once it has as many arguments as its arity. Before that it is partially
+    // applied, and more arguments would be a type error.
+    if args.len() != b.arity() {
+        return AppliedBuiltin(BuiltinClosure { b, args, env });
+    }
+    // The arguments of the builtin; the length of the pattern must be its arity.
+    fn take<'a, 'cx, const N: usize>(
+        args: &'a [Nir<'cx>],
+    ) -> &'a [Nir<'cx>; N] {
+        args.try_into()
+            .expect("pattern length differs from the arity")
+    }
+
+    let ret = match b {
+        Builtin::Bool
+        | Builtin::Natural
+        | Builtin::Integer
+        | Builtin::Double
+        | Builtin::Text => Ret::NirKind(BuiltinType(b)),
+        Builtin::Optional => {
+            let [t] = take(&args);
+            Ret::NirKind(OptionalType(t.clone()))
+        }
+        Builtin::List => {
+            let [t] = take(&args);
+            Ret::NirKind(ListType(t.clone()))
+        }
 
-        (Builtin::OptionalNone, [t]) => {
+        Builtin::OptionalNone => {
+            let [t] = take(&args);
             Ret::NirKind(EmptyOptionalLit(t.clone()))
         }
-        (Builtin::NaturalIsZero, [n]) => match &*n.kind() {
-            Num(Natural(n)) => Ret::NirKind(Num(Bool(*n == 0))),
-            _ => Ret::DoneAsIs,
-        },
-        (Builtin::NaturalEven, [n]) => match &*n.kind() {
-            Num(Natural(n)) => Ret::NirKind(Num(Bool(*n % 2 == 0))),
-            _ => Ret::DoneAsIs,
-        },
-        (Builtin::NaturalOdd, [n]) => match &*n.kind() {
-            Num(Natural(n)) => Ret::NirKind(Num(Bool(*n % 2 != 0))),
-            _ => Ret::DoneAsIs,
-        },
-        (Builtin::NaturalToInteger, [n]) => match &*n.kind() {
-            Num(Natural(n)) => Ret::NirKind(Num(Integer(*n as i64))),
-            _ => Ret::DoneAsIs,
-        },
-        (Builtin::NaturalShow, [n]) => match &*n.kind() {
-            Num(Natural(n)) => Ret::Nir(Nir::from_text(n)),
-            _ => Ret::DoneAsIs,
-        },
-        (Builtin::NaturalSubtract, [a, b]) => match (&*a.kind(), &*b.kind()) {
-            (Num(Natural(a)), Num(Natural(b))) => {
-                Ret::NirKind(Num(Natural(if b > a { b - a } else { 0 })))
+        _ if !env.options().reduce_builtins => Ret::DoneAsIs,
+        Builtin::NaturalIsZero => {
+            let [n] = take(&args);
+            match &*n.kind() {
+                Num(Natural(n)) => Ret::NirKind(Num(Bool(n.is_zero()))),
+                _ => Ret::DoneAsIs,
             }
-            (Num(Natural(0)), _) => Ret::Nir(b.clone()),
-            (_, Num(Natural(0))) => Ret::NirKind(Num(Natural(0))),
-            _ if a == b => Ret::NirKind(Num(Natural(0))),
-            _ => Ret::DoneAsIs,
-        },
-        (Builtin::IntegerShow, [n]) => match &*n.kind() {
-            Num(Integer(n)) => {
-                let s = if *n < 0 {
-                    n.to_string()
-                } else {
-                    format!("+{}", n)
-                };
-                Ret::Nir(Nir::from_text(s))
+        }
+        Builtin::NaturalEven => {
+            let [n] = take(&args);
+            match &*n.kind() {
+                Num(Natural(n)) => {
+                    Ret::NirKind(Num(Bool((n % 2u32).is_zero())))
+                }
+                _ => Ret::DoneAsIs,
             }
-            _ => Ret::DoneAsIs,
-        },
-        (Builtin::IntegerToDouble, [n]) => match &*n.kind() {
-            Num(Integer(n)) => {
-                Ret::NirKind(Num(Double(NaiveDouble::from(*n as f64))))
+        }
+        Builtin::NaturalOdd => {
+            let [n] = take(&args);
+            match &*n.kind() {
+                Num(Natural(n)) => {
+                    Ret::NirKind(Num(Bool(!(n % 2u32).is_zero())))
+                }
+                _ => Ret::DoneAsIs,
+            }
+        }
+        Builtin::NaturalToInteger => {
+            let [n] = take(&args);
+            match &*n.kind() {
+                Num(Natural(n)) => Ret::NirKind(Num(Integer(n.clone().into()))),
+                _ => Ret::DoneAsIs,
+            }
+        }
+        Builtin::NaturalShow => {
+            let [n] = take(&args);
+            match &*n.kind() {
+                Num(Natural(n)) => Ret::Nir(Nir::from_text(n)),
+                _ => Ret::DoneAsIs,
+            }
+        }
+        Builtin::NaturalSubtract => {
+            let [a, b] = take(&args);
+            match (&*a.kind(), &*b.kind()) {
+                (Num(Natural(a)), Num(Natural(b))) => {
+                    Ret::NirKind(Num(Natural(if b > a {
+                        b - a
+                    } else {
+                        Zero::zero()
+                    })))
+                }
+                (Num(Natural(n)), _) if n.is_zero() => Ret::Nir(b.clone()),
+                (_, Num(Natural(n))) if n.is_zero() => Ret::Nir(b.clone()),
+                _ if a == b => Ret::NirKind(Num(Natural(Zero::zero()))),
+                _ => Ret::DoneAsIs,
+            }
+        }
+        Builtin::IntegerShow => {
+            let [n] = take(&args);
+            match &*n.kind() {
+                Num(Integer(n)) => {
+                    let s = if n.is_negative() {
+                        n.to_string()
+                    } else {
+                        format!("+{}", n)
+                    };
+                    Ret::Nir(Nir::from_text(s))
+                }
+                _ => Ret::DoneAsIs,
+            }
+        }
+        Builtin::IntegerToDouble => {
+            let [n] = take(&args);
+            match &*n.kind() {
+                // This rounds to the nearest double, ties to even, as the standard requires for
+                // integers that don't fit in the mantissa. Larger ones become infinite.
+                Num(Integer(n)) => {
+                    let d = n.to_f64().unwrap_or(f64::NAN);
+                    Ret::NirKind(Num(Double(NaiveDouble::from(d))))
+                }
+                _ => Ret::DoneAsIs,
+            }
+        }
+        Builtin::IntegerNegate => {
+            let [n] = take(&args);
+            match &*n.kind() {
+                Num(Integer(n)) => Ret::NirKind(Num(Integer(-n))),
+                _ => Ret::DoneAsIs,
+            }
+        }
+        Builtin::IntegerClamp => {
+            let [n] = take(&args);
+            match &*n.kind() {
+                Num(Integer(n)) => Ret::NirKind(Num(Natural(
+                    n.to_biguint().unwrap_or_default(),
+                ))),
+                _ => Ret::DoneAsIs,
             }
-            _ => Ret::DoneAsIs,
-        },
-        (Builtin::IntegerNegate, [n]) => match &*n.kind() {
-            Num(Integer(n)) => Ret::NirKind(Num(Integer(-n))),
-            _ => Ret::DoneAsIs,
-        },
-        (Builtin::IntegerClamp, [n]) => match &*n.kind() {
-            Num(Integer(n)) => {
-                Ret::NirKind(Num(Natural((*n).try_into().unwrap_or(0))))
+        }
+        Builtin::DoubleShow => {
+            let [n] = take(&args);
+            match &*n.kind() {
+                Num(Double(n)) => Ret::Nir(Nir::from_text(n)),
+                _ => Ret::DoneAsIs,
             }
-            _ => Ret::DoneAsIs,
-        },
-        (Builtin::DoubleShow, [n]) => match &*n.kind() {
-            Num(Double(n)) => Ret::Nir(Nir::from_text(n)),
-            _ => Ret::DoneAsIs,
-        },
-        (Builtin::TextShow, [v]) => match &*v.kind() {
-            TextLit(tlit) => {
-                if let Some(s) = tlit.as_text() {
-                    // Printing InterpolatedText takes care of all the escaping
-                    let txt: InterpolatedText<Expr> =
-                        std::iter::once(InterpolatedTextContents::Text(s))
-                            .collect();
-                    Ret::Nir(Nir::from_text(txt))
-                } else {
-                    Ret::DoneAsIs
+        }
+        Builtin::TextShow => {
+            let [v] = take(&args);
+            match &*v.kind() {
+                TextLit(tlit) => {
+                    if let Some(s) = tlit.as_text() {
+                        // Printing InterpolatedText takes care of all the escaping
+                        let txt: InterpolatedText<Expr> =
+                            std::iter::once(InterpolatedTextContents::Text(s))
+                                .collect();
+                        Ret::Nir(Nir::from_text(txt))
+                    } else {
+                        Ret::DoneAsIs
+                    }
                 }
+                _ => Ret::DoneAsIs,
             }
-            _ => Ret::DoneAsIs,
-        },
-        (Builtin::TextReplace, [needle, replacement, haystack]) => {
+        }
+        Builtin::TextReplace => {
+            let [needle, replacement, haystack] = take(&args);
             // Helper to match a Nir as a text literal
             fn nir_to_string(n: &Nir) -> Option<String> {
                 match &*n.kind() {
@@ -464,33 +771,46 @@ fn apply_builtin<'cx>(
                 _ => Ret::DoneAsIs,
             }
         }
-        (Builtin::ListLength, [_, l]) => match &*l.kind() {
-            EmptyListLit(_) => Ret::NirKind(Num(Natural(0))),
-            NEListLit(xs) => Ret::NirKind(Num(Natural(xs.len() as u64))),
-            _ => Ret::DoneAsIs,
-        },
-        (Builtin::ListHead, [_, l]) => match &*l.kind() {
-            EmptyListLit(n) => Ret::NirKind(EmptyOptionalLit(n.clone())),
-            NEListLit(xs) => {
-                Ret::NirKind(NEOptionalLit(xs.iter().next().unwrap().clone()))
+        Builtin::ListLength => {
+            let [_, l] = take(&args);
+            match &*l.kind() {
+                EmptyListLit(_) => Ret::NirKind(Num(Natural(Zero::zero()))),
+                NEListLit(xs) => Ret::NirKind(Num(Natural(xs.len().into()))),
+                _ => Ret::DoneAsIs,
+            }
+        }
+        Builtin::ListHead => {
+            let [_, l] = take(&args);
+            match &*l.kind() {
+                EmptyListLit(n) => Ret::NirKind(EmptyOptionalLit(n.clone())),
+                NEListLit(xs) => Ret::NirKind(NEOptionalLit(
+                    xs.iter().next().unwrap().clone(),
+                )),
+                _ => Ret::DoneAsIs,
             }
-            _ => Ret::DoneAsIs,
-        },
-        (Builtin::ListLast, [_, l]) => match &*l.kind() {
-            EmptyListLit(n) => Ret::NirKind(EmptyOptionalLit(n.clone())),
-            NEListLit(xs) => Ret::NirKind(NEOptionalLit(
-                xs.iter().rev().next().unwrap().clone(),
-            )),
-            _ => Ret::DoneAsIs,
-        },
-        (Builtin::ListReverse, [_, l]) => match &*l.kind() {
-            EmptyListLit(n) => Ret::NirKind(EmptyListLit(n.clone())),
-            NEListLit(xs) => {
-                Ret::NirKind(NEListLit(xs.iter().rev().cloned().collect()))
+        }
+        Builtin::ListLast => {
+            let [_, l] = take(&args);
+            match &*l.kind() {
+                EmptyListLit(n) => Ret::NirKind(EmptyOptionalLit(n.clone())),
+                NEListLit(xs) => Ret::NirKind(NEOptionalLit(
+                    xs.iter().rev().next().unwrap().clone(),
+                )),
+                _ => Ret::DoneAsIs,
             }
-            _ => Ret::DoneAsIs,
-        },
-        (Builtin::ListIndexed, [t, l]) => {
+        }
+        Builtin::ListReverse => {
+            let [_, l] = take(&args);
+            match &*l.kind() {
+                EmptyListLit(n) => Ret::NirKind(EmptyListLit(n.clone())),
+                NEListLit(xs) => {
+                    Ret::NirKind(NEListLit(xs.iter().rev().cloned().collect()))
+                }
+                _ => Ret::DoneAsIs,
+            }
+        }
+        Builtin::ListIndexed => {
+            let [t, l] = take(&args);
             match l.kind() {
                 EmptyListLit(_) | NEListLit(_) => {
                     // Construct the returned record type: { index: Natural, value: t }
@@ -512,7 +832,7 @@ fn apply_builtin<'cx>(
                                     let mut kvs = HashMap::new();
                                     kvs.insert(
                                         "index".into(),
-                                        Nir::from_kind(Num(Natural(i as u64))),
+                                        Nir::from_kind(Num(Natural(i.into()))),
                                     );
                                     kvs.insert("value".into(), e.clone());
                                     Nir::from_kind(RecordLit(kvs))
@@ -526,7 +846,8 @@ fn apply_builtin<'cx>(
                 _ => Ret::DoneAsIs,
             }
         }
-        (Builtin::ListBuild, [t, f]) => {
+        Builtin::ListBuild => {
+            let [t, f] = take(&args);
             let list_t = Nir::from_builtin(cx, Builtin::List).app(t.clone());
             Ret::Nir(
                 f.app(list_t)
@@ -542,39 +863,47 @@ fn apply_builtin<'cx>(
                     .app(EmptyListLit(t.clone()).into_nir()),
             )
         }
-        (Builtin::ListFold, [_, l, _, cons, nil]) => match &*l.kind() {
-            EmptyListLit(_) => Ret::Nir(nil.clone()),
-            NEListLit(xs) => {
-                let mut v = nil.clone();
-                for x in xs.iter().cloned().rev() {
-                    v = cons.app(x).app(v);
+        Builtin::ListFold => {
+            let [_, l, _, cons, nil] = take(&args);
+            match &*l.kind() {
+                EmptyListLit(_) => Ret::Nir(nil.clone()),
+                NEListLit(xs) => {
+                    let mut v = nil.clone();
+                    for x in xs.iter().cloned().rev() {
+                        v = cons.app(x).app(v);
+                    }
+                    Ret::Nir(v)
                 }
-                Ret::Nir(v)
+                _ => Ret::DoneAsIs,
             }
-            _ => Ret::DoneAsIs,
-        },
-        (Builtin::NaturalBuild, [f]) => Ret::Nir(
-            f.app(Nir::from_builtin(cx, Builtin::Natural))
-                .app(make_closure(make_closure!(
-                    λ(x : Natural) ->
-                    1 + var(x)
-                )))
-                .app(Num(Natural(0)).into_nir()),
-        ),
+        }
+        Builtin::NaturalBuild => {
+            let [f] = take(&args);
+            Ret::Nir(
+                f.app(Nir::from_builtin(cx, Builtin::Natural))
+                    .app(make_closure(make_closure!(
+                        λ(x : Natural) ->
+                        1 + var(x)
+                    )))
+                    .app(Num(Natural(Zero::zero())).into_nir()),
+            )
+        }
 
-        (Builtin::NaturalFold, [n, t, succ, zero]) => match &*n.kind() {
-            Num(Natural(0)) => Ret::Nir(zero.clone()),
-            Num(Natural(n)) => {
-                let fold = Nir::from_builtin(cx, Builtin::NaturalFold)
-                    .app(Num(Natural(n - 1)).into_nir())
-                    .app(t.clone())
-                    .app(succ.clone())
-                    .app(zero.clone());
-                Ret::Nir(succ.app(fold))
+        Builtin::NaturalFold => {
+            let [n, t, succ, zero] = take(&args);
+            match &*n.kind() {
+                Num(Natural(n)) if n.is_zero() => Ret::Nir(zero.clone()),
+                Num(Natural(n)) => {
+                    let fold = Nir::from_builtin(cx, Builtin::NaturalFold)
+                        .app(Num(Natural(n - 1u32)).into_nir())
+                        .app(t.clone())
+                        .app(succ.clone())
+                        .app(zero.clone());
+                    Ret::Nir(succ.app(fold))
+                }
+                _ => Ret::DoneAsIs,
             }
-            _ => Ret::DoneAsIs,
-        },
-        _ => Ret::DoneAsIs,
+        }
     };
     match ret {
         Ret::NirKind(v) => v,
@@ -583,9 +912,14 @@ fn apply_builtin<'cx>(
     }
 }
 
+impl<'cx> BuiltinClosure<'cx> {
+    pub(crate) fn conv(&self, other: &Self, memo: &mut ConvMemo<'cx>) -> bool {
+        self.b == other.b && conv_args(&self.args, &other.args, memo)
+    }
+}
 impl<'cx> std::cmp::PartialEq for BuiltinClosure<'cx> {
     fn eq(&self, other: &Self) -> bool {
-        self.b == other.b && self.args == other.args
+        self.conv(other, &mut ConvMemo::default())
     }
 }
 impl<'cx> std::cmp::Eq for BuiltinClosure<'cx> {}
@@ -1,10 +1,12 @@
 use elsa::vec::FrozenVec;
 use once_cell::sync::OnceCell;
+use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::ops::{Deref, Index};
 
-use crate::semantics::{Import, ImportLocation, ImportNode};
-use crate::syntax::Span;
+use crate::builtins::CustomBuiltins;
+use crate::semantics::{Import, ImportLocation, ImportNode, Nir, Type};
+use crate::syntax::{Label, Span};
 use crate::Typed;
 
 /////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -16,6 +18,10 @@ pub struct CtxtS<'cx> {
     imports: FrozenVec<Box<StoredImport<'cx>>>,
     import_alternatives: FrozenVec<Box<StoredImportAlternative<'cx>>>,
     import_results: FrozenVec<Box<StoredImportResult<'cx>>>,
+    custom_builtins: CustomBuiltins,
+    /// The types of `custom_builtins`, computed the first time one is needed.
+    custom_builtin_types:
+        once_cell::unsync::OnceCell<HashMap<Label, Type<'cx>>>,
 }
 
 /// Context for the dhall compiler. Stores various global maps.
@@ -29,6 +35,32 @@ impl Ctxt<'_> {
         let cx = Ctxt(&cx);
         f(cx)
     }
+    /// Like `with_new`, but the given builtins are available to the expressions typechecked and
+    /// normalized in this context.
+    pub fn with_custom_builtins<T>(
+        builtins: CustomBuiltins,
+        f: impl for<'cx> FnOnce(Ctxt<'cx>) -> T,
+    ) -> T {
+        let cx = CtxtS {
+            custom_builtins: builtins,
+            ..CtxtS::default()
+        };
+        let cx = Ctxt(&cx);
+        f(cx)
+    }
+}
+impl<'cx> Ctxt<'cx> {
+    pub fn custom_builtins(self) -> &'cx CustomBuiltins {
+        &self.0.custom_builtins
+    }
+    /// The type of the custom builtin `name`, which must have been registered.
+    pub(crate) fn custom_builtin_type(self, name: &Label) -> &'cx Type<'cx> {
+        let types = self
+            .0
+            .custom_builtin_types
+            .get_or_init(|| self.custom_builtins().types(self));
+        &types[name]
+    }
 }
 impl<'cx> Deref for Ctxt<'cx> {
     type Target = &'cx CtxtS<'cx>;
@@ -93,10 +125,7 @@ impl<'cx> StoredImport<'cx> {
             .expect("imports should all have been resolved at this stage")
     }
     /// Store the result of fetching this import.
-    pub fn set_result(
-        &self,
-        res: StoredImportResult<'cx>,
-    ) -> ImportResultId<'cx> {
+    pub fn set_result(&self, res: Typed<'cx>) -> ImportResultId<'cx> {
         let res = self.cx.push_import_result(res);
         self.set_resultid(res);
         res
@@ -190,14 +219,34 @@ impl<'cx> Index<ImportAlternativeId<'cx>> for CtxtS<'cx> {
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct ImportResultId<'cx>(usize, PhantomData<&'cx ()>);
 
-type StoredImportResult<'cx> = Typed<'cx>;
+/// What's stored for each `ImportResultId`: the typechecked contents of the import.
+pub struct StoredImportResult<'cx> {
+    typed: Typed<'cx>,
+    /// The value of the import, shared by all the places that use it. Imports are closed, so it
+    /// doesn't depend on where they are used.
+    nir: once_cell::unsync::OnceCell<Nir<'cx>>,
+}
+
+impl<'cx> StoredImportResult<'cx> {
+    /// The value of the import. It is only evaluated the first time it is needed.
+    pub fn nir(&self, cx: Ctxt<'cx>) -> &Nir<'cx> {
+        self.nir.get_or_init(|| self.typed.hir.eval_closed_expr(cx))
+    }
+}
+impl<'cx> Deref for StoredImportResult<'cx> {
+    type Target = Typed<'cx>;
+    fn deref(&self) -> &Typed<'cx> {
+        &self.typed
+    }
+}
 
 impl<'cx> Ctxt<'cx> {
     /// Store the result of fetching an import.
-    pub fn push_import_result(
-        self,
-        res: StoredImportResult<'cx>,
-    ) -> ImportResultId<'cx> {
+    pub fn push_import_result(self, res: Typed<'cx>) -> ImportResultId<'cx> {
+        let res = StoredImportResult {
+            typed: res,
+            nir: Default::default(),
+        };
         let id = self.0.import_results.len();
         self.0.import_results.push(Box::new(res));
         ImportResultId(id, PhantomData)
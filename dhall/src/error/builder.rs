@@ -63,6 +63,18 @@ impl ErrorBuilder {
         }
     }
 
+    pub fn new_warning(message: impl ToString) -> Self {
+        ErrorBuilder {
+            title: FreeAnnotation {
+                message: message.to_string(),
+                annotation_type: AnnotationType::Warning,
+            },
+            annotations: Vec::new(),
+            footer: Vec::new(),
+            consumed: false,
+        }
+    }
+
     pub fn span_annot(
         &mut self,
         span: Span,
@@ -100,6 +112,13 @@ impl ErrorBuilder {
     ) -> &mut Self {
         self.span_annot(span, message, AnnotationType::Error)
     }
+    pub fn span_warn(
+        &mut self,
+        span: Span,
+        message: impl ToString,
+    ) -> &mut Self {
+        self.span_annot(span, message, AnnotationType::Warning)
+    }
     pub fn span_help(
         &mut self,
         span: Span,
@@ -23,6 +23,7 @@ pub enum ErrorKind {
     Resolve(ImportError),
     Typecheck(TypeError),
     Cache(CacheError),
+    NotALiteral(NotALiteral),
 }
 
 #[derive(Debug)]
@@ -51,6 +52,7 @@ pub enum EncodeError {
 #[derive(Debug)]
 pub struct TypeError {
     message: TypeMessage,
+    trace: Vec<String>,
 }
 
 /// The specific type error
@@ -59,6 +61,13 @@ pub enum TypeMessage {
     Custom(String),
 }
 
+/// Extracting a Rust value from an expression that is not a literal of the expected type.
+#[derive(Debug)]
+pub struct NotALiteral {
+    expected: &'static str,
+    found: String,
+}
+
 #[derive(Debug)]
 pub enum CacheError {
     MissingConfiguration,
@@ -77,7 +86,21 @@ impl Error {
 
 impl TypeError {
     pub fn new(message: TypeMessage) -> Self {
-        TypeError { message }
+        TypeError {
+            message,
+            trace: Vec::new(),
+        }
+    }
+    /// The constructs that were being typechecked when the error occurred, outermost first, e.g.
+    /// `["RecordLit field \"x\"", "BoolIf then-branch"]`. Only recorded when typechecking with
+    /// [`TypecheckOptions::trace`](crate::TypecheckOptions::trace) enabled; empty otherwise.
+    pub fn trace(&self) -> &[String] {
+        &self.trace
+    }
+    /// Record that the error occurred inside `step`. Called as the error propagates outwards.
+    pub(crate) fn push_trace(mut self, step: String) -> Self {
+        self.trace.insert(0, step);
+        self
     }
 }
 
@@ -87,12 +110,42 @@ impl std::fmt::Display for TypeError {
         let msg = match &self.message {
             Custom(s) => format!("Type error: {}", s),
         };
-        write!(f, "{}", msg)
+        write!(f, "{}", msg)?;
+        if !self.trace.is_empty() {
+            write!(f, "\nwhile typechecking: {}", self.trace.join(" > "))?;
+        }
+        Ok(())
     }
 }
 
 impl std::error::Error for TypeError {}
 
+impl NotALiteral {
+    pub(crate) fn new(expected: &'static str, found: String) -> Self {
+        NotALiteral { expected, found }
+    }
+    /// The type of literal that was expected, e.g. `"Natural"`.
+    pub fn expected(&self) -> &str {
+        self.expected
+    }
+    /// The expression that was found instead.
+    pub fn found(&self) -> &str {
+        &self.found
+    }
+}
+
+impl std::fmt::Display for NotALiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "expected a {} literal, found `{}`",
+            self.expected, self.found
+        )
+    }
+}
+
+impl std::error::Error for NotALiteral {}
+
 impl std::fmt::Display for EncodeError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         let msg = match self {
@@ -114,6 +167,7 @@ impl std::fmt::Display for Error {
             ErrorKind::Resolve(err) => write!(f, "{:?}", err),
             ErrorKind::Typecheck(err) => write!(f, "{}", err),
             ErrorKind::Cache(err) => write!(f, "{:?}", err),
+            ErrorKind::NotALiteral(err) => write!(f, "{}", err),
         }
     }
 }
@@ -159,6 +213,11 @@ impl From<TypeError> for Error {
         ErrorKind::Typecheck(err).into()
     }
 }
+impl From<NotALiteral> for Error {
+    fn from(err: NotALiteral) -> Error {
+        ErrorKind::NotALiteral(err).into()
+    }
+}
 impl From<CacheError> for Error {
     fn from(err: CacheError) -> Error {
         ErrorKind::Cache(err).into()
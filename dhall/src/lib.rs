@@ -17,10 +17,12 @@ pub mod builtins;
 pub mod ctxt;
 pub mod error;
 pub mod operations;
+pub mod prelude;
 pub mod semantics;
 pub mod syntax;
 pub mod utils;
 
+use std::collections::HashMap;
 use std::path::Path;
 use url::Url;
 
@@ -28,7 +30,10 @@ use crate::error::{Error, TypeError};
 use crate::semantics::parse;
 use crate::semantics::resolve;
 use crate::semantics::resolve::ImportLocation;
-use crate::semantics::{typecheck, typecheck_with, Hir, Nir, Tir, Type};
+use crate::semantics::{
+    typecheck, typecheck_collecting, typecheck_with, typecheck_with_options,
+    typecheck_with_warnings, Hir, Nir, NzEnv, Tir, TyEnv, Type,
+};
 use crate::syntax::Expr;
 
 pub use ctxt::*;
@@ -60,6 +65,39 @@ pub struct Normalized<'cx>(Nir<'cx>);
 pub struct ToExprOptions {
     /// Whether to convert all variables to `_`
     pub alpha: bool,
+    /// When `alpha` is set, name each binder after its depth, as `x`, `x1`, `x2`, ..., instead of
+    /// `_`. This is easier to read and still canonical, but it isn't the standard alpha-normal form
+    /// so it must not be used for hashing.
+    pub numbered_names: bool,
+}
+
+/// Controls normalization of a `Typed` expression
+#[derive(Debug, Copy, Clone)]
+pub struct NormalizeOptions {
+    /// Whether to evaluate builtin functions like `Natural/fold` when applied to concrete
+    /// arguments. When disabled, such applications are left as they are.
+    pub reduce_builtins: bool,
+}
+
+/// Controls typechecking of a `Resolved` expression
+#[derive(Debug, Copy, Clone, Default)]
+pub struct TypecheckOptions {
+    /// Whether to record, in type errors, the path of constructs that were being typechecked when
+    /// the error occurred. See [`TypeError::trace`].
+    pub trace: bool,
+    /// Whether to accept `merge` handlers for alternatives that the union doesn't have, e.g. to
+    /// keep working when an alternative is removed from a union. They are reported as warnings
+    /// instead of errors; see [`Resolved::typecheck_with_warnings`]. Imported expressions are
+    /// always checked strictly.
+    pub allow_unused_handlers: bool,
+}
+
+impl Default for NormalizeOptions {
+    fn default() -> Self {
+        NormalizeOptions {
+            reduce_builtins: true,
+        }
+    }
 }
 
 impl Parsed {
@@ -94,11 +132,49 @@ impl Parsed {
     ) -> Result<Resolved<'cx>, Error> {
         resolve::skip_resolve(cx, self)
     }
+    /// Typecheck the expression without resolving its imports, treating each import as an opaque
+    /// value of the type given in `import_types`. Returns the type of the expression.
+    pub fn typecheck_with_import_types<'cx>(
+        &self,
+        cx: Ctxt<'cx>,
+        import_types: &HashMap<syntax::Import<Expr>, Expr>,
+    ) -> Result<Expr, Error> {
+        resolve::typecheck_with_import_types(cx, self, import_types)
+    }
+    /// Normalize the expression, whose free variables are given by `context`. See
+    /// [`semantics::normalize_in_context`].
+    pub fn normalize_in_context<'cx>(
+        &self,
+        cx: Ctxt<'cx>,
+        context: &[(syntax::Label, semantics::ContextItem)],
+    ) -> Result<Expr, Error> {
+        resolve::normalize_in_context(cx, context, self)
+    }
 
     /// Converts a value back to the corresponding AST expression.
     pub fn to_expr(&self) -> Expr {
         self.0.clone()
     }
+    /// Encodes the expression in the standard binary format, as used in `.dhallb` files and in
+    /// the import cache. The standard no longer includes a version tag in the encoding.
+    pub fn to_binary(&self) -> Result<Vec<u8>, Error> {
+        Ok(syntax::binary::encode(&self.0)?)
+    }
+    /// Writes the expression to a `.dhallb` file. See `parse_binary_file` to read it back.
+    pub fn write_binary_file(&self, f: &Path) -> Result<(), Error> {
+        std::fs::write(f, self.to_binary()?)?;
+        Ok(())
+    }
+
+    /// Replace the `env:VAR` imports whose variable is in `overrides` before resolution. See
+    /// [`Expr::with_env_overrides`].
+    pub fn with_env_overrides(
+        self,
+        overrides: &HashMap<String, Expr>,
+    ) -> Parsed {
+        let Parsed(expr, import_location) = self;
+        Parsed(expr.with_env_overrides(overrides), import_location)
+    }
 
     pub fn add_let_binding(self, label: syntax::Label, value: Expr) -> Parsed {
         let Parsed(expr, import_location) = self;
@@ -110,6 +186,32 @@ impl<'cx> Resolved<'cx> {
     pub fn typecheck(&self, cx: Ctxt<'cx>) -> Result<Typed<'cx>, TypeError> {
         Ok(Typed::from_tir(typecheck(cx, &self.0)?))
     }
+    /// Like `typecheck`, but with the provided options.
+    pub fn typecheck_with_options(
+        &self,
+        cx: Ctxt<'cx>,
+        opts: TypecheckOptions,
+    ) -> Result<Typed<'cx>, TypeError> {
+        Ok(Typed::from_tir(typecheck_with_options(cx, &self.0, opts)?))
+    }
+    /// Like `typecheck_with_options`, but also returns the warnings about problems the options
+    /// told us to tolerate.
+    pub fn typecheck_with_warnings(
+        &self,
+        cx: Ctxt<'cx>,
+        opts: TypecheckOptions,
+    ) -> Result<(Typed<'cx>, Vec<String>), TypeError> {
+        let (tir, warnings) = typecheck_with_warnings(cx, &self.0, opts)?;
+        Ok((Typed::from_tir(tir), warnings))
+    }
+    /// Like `typecheck`, but reports all the errors in independent parts of the expression
+    /// instead of only the first. See [`semantics::typecheck_collecting`].
+    pub fn typecheck_collecting(
+        &self,
+        cx: Ctxt<'cx>,
+    ) -> Result<Typed<'cx>, Vec<TypeError>> {
+        Ok(Typed::from_tir(typecheck_collecting(cx, &self.0)?))
+    }
     pub fn typecheck_with(
         self,
         cx: Ctxt<'cx>,
@@ -117,6 +219,42 @@ impl<'cx> Resolved<'cx> {
     ) -> Result<Typed<'cx>, TypeError> {
         Ok(Typed::from_tir(typecheck_with(cx, &self.0, ty)?))
     }
+    /// Find the `let`-bound functions whose argument type could be abstracted into a type
+    /// variable. Returns the name of each binding, the span of the `let` and the suggested
+    /// signature. See [`semantics::generalizable_lets`] for the heuristic.
+    pub fn generalizable_lets(
+        &self,
+        cx: Ctxt<'cx>,
+    ) -> Result<Vec<(syntax::Label, syntax::Span, Expr)>, Error> {
+        semantics::generalizable_lets(cx, &self.0)
+    }
+    /// Typecheck the expression, additionally checking that it is a type, i.e. that its own type
+    /// is `Type`, `Kind` or `Sort`. Useful for APIs that only accept Dhall types.
+    pub fn typecheck_type(
+        &self,
+        cx: Ctxt<'cx>,
+    ) -> Result<Typed<'cx>, TypeError> {
+        let tir = typecheck(cx, &self.0)?;
+        tir.ensure_is_type(&TyEnv::new(cx))?;
+        Ok(Typed::from_tir(tir))
+    }
+    /// The name and normalized type of each of the `let` bindings at the top of the expression.
+    /// See [`semantics::binding_types`].
+    pub fn binding_types(
+        &self,
+        cx: Ctxt<'cx>,
+    ) -> Result<Vec<(syntax::Label, Expr)>, TypeError> {
+        semantics::binding_types(cx, &self.0)
+    }
+    /// Typecheck and normalize the expression in one go, returning its normalized type and
+    /// normalized value.
+    pub fn infer_and_eval(
+        &self,
+        cx: Ctxt<'cx>,
+    ) -> Result<(Normalized<'cx>, Normalized<'cx>), TypeError> {
+        let typed = self.typecheck(cx)?;
+        Ok((typed.get_type()?, typed.normalize(cx)))
+    }
     /// Converts a value back to the corresponding AST expression.
     pub fn to_expr(&self, cx: Ctxt<'cx>) -> Expr {
         self.0.to_expr_noopts(cx)
@@ -134,10 +272,18 @@ impl<'cx> Typed<'cx> {
     pub fn normalize(&self, cx: Ctxt<'cx>) -> Normalized<'cx> {
         Normalized(self.hir.eval_closed_expr(cx))
     }
+    /// Like `normalize`, but with the provided options.
+    pub fn normalize_with(
+        &self,
+        cx: Ctxt<'cx>,
+        opts: NormalizeOptions,
+    ) -> Normalized<'cx> {
+        Normalized(self.hir.eval(NzEnv::with_options(cx, opts)))
+    }
 
     /// Converts a value back to the corresponding AST expression.
     fn to_expr(&self, cx: Ctxt<'cx>) -> Expr {
-        self.hir.to_expr(cx, ToExprOptions { alpha: false })
+        self.hir.to_expr(cx, ToExprOptions::default())
     }
 
     pub fn as_hir(&self) -> &Hir<'cx> {
@@ -165,7 +311,13 @@ impl<'cx> Normalized<'cx> {
     }
     /// Converts a value back to the corresponding AST expression, alpha-normalizing in the process.
     pub fn to_expr_alpha(&self, cx: Ctxt<'cx>) -> Expr {
-        self.0.to_expr(cx, ToExprOptions { alpha: true })
+        self.0.to_expr(
+            cx,
+            ToExprOptions {
+                alpha: true,
+                ..Default::default()
+            },
+        )
     }
 }
 
@@ -198,6 +350,13 @@ impl From<Parsed> for Expr {
     }
 }
 
+impl std::convert::TryFrom<&str> for Parsed {
+    type Error = Error;
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        Parsed::parse_str(s)
+    }
+}
+
 impl<'cx> Eq for Normalized<'cx> {}
 impl<'cx> PartialEq for Normalized<'cx> {
     fn eq(&self, other: &Self) -> bool {
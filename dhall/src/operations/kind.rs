@@ -5,6 +5,7 @@ use crate::syntax::{trivial_result, Label};
 // Definition order must match precedence order for
 // pretty-printing to work correctly
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BinOp {
     /// x === y
     Equivalence,
@@ -36,6 +37,7 @@ pub enum BinOp {
 
 /// Operations
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum OpKind<SubExpr> {
     ///  `f a`
     App(SubExpr, SubExpr),
@@ -1,4 +1,5 @@
 use itertools::Itertools;
+use num_traits::{One, Zero};
 use std::collections::HashMap;
 use std::iter::once;
 
@@ -33,15 +34,15 @@ fn normalize_binop<'cx>(o: BinOp, x: Nir<'cx>, y: Nir<'cx>) -> Ret<'cx> {
         (BoolNE, Num(Bool(x)), Num(Bool(y))) => ret_kind(Num(Bool(x != y))),
         (BoolNE, _, _) if x == y => ret_kind(Num(Bool(false))),
 
-        (NaturalPlus, Num(Natural(0)), _) => ret_nir(y),
-        (NaturalPlus, _, Num(Natural(0))) => ret_nir(x),
+        (NaturalPlus, Num(Natural(n)), _) if n.is_zero() => ret_nir(y),
+        (NaturalPlus, _, Num(Natural(n))) if n.is_zero() => ret_nir(x),
         (NaturalPlus, Num(Natural(x)), Num(Natural(y))) => {
             ret_kind(Num(Natural(x + y)))
         }
-        (NaturalTimes, Num(Natural(0)), _) => ret_kind(Num(Natural(0))),
-        (NaturalTimes, _, Num(Natural(0))) => ret_kind(Num(Natural(0))),
-        (NaturalTimes, Num(Natural(1)), _) => ret_nir(y),
-        (NaturalTimes, _, Num(Natural(1))) => ret_nir(x),
+        (NaturalTimes, Num(Natural(n)), _) if n.is_zero() => ret_nir(x),
+        (NaturalTimes, _, Num(Natural(n))) if n.is_zero() => ret_nir(y),
+        (NaturalTimes, Num(Natural(n)), _) if n.is_one() => ret_nir(y),
+        (NaturalTimes, _, Num(Natural(n))) if n.is_one() => ret_nir(x),
         (NaturalTimes, Num(Natural(x)), Num(Natural(y))) => {
             ret_kind(Num(Natural(x * y)))
         }
@@ -183,6 +184,10 @@ fn normalize_field<'cx>(v: &Nir<'cx>, field: &Label) -> Ret<'cx> {
             },
             _ => nothing_to_do(),
         },
+        // We don't push the selection into the branches of an `if`, e.g. to reduce
+        // `(if c then { a = 1 } else { a = 2 }).a` to `if c then 1 else 2`: the standard doesn't,
+        // and normal forms must match the standard's for hashes and equivalence to agree.
+        // Likewise `(r with a = 1).b` stays stuck instead of becoming `r.b`.
         _ => nothing_to_do(),
     }
 }
@@ -249,7 +254,10 @@ pub fn normalize_operation<'cx>(opkind: OpKind<Nir<'cx>>) -> Ret<'cx> {
                     .sorted_by_key(|(k, _)| *k)
                     .map(|(k, v)| {
                         let mut rec = HashMap::new();
-                        rec.insert("mapKey".into(), Nir::from_text(k));
+                        rec.insert(
+                            "mapKey".into(),
+                            Nir::from_text(String::from(k)),
+                        );
                         rec.insert("mapValue".into(), v.clone());
                         Nir::from_kind(NirKind::RecordLit(rec))
                     })
@@ -9,7 +9,7 @@ use crate::semantics::{
     merge_maps, mk_span_err, mkerr, Binder, Closure, Hir, HirKind, Nir,
     NirKind, Tir, TyEnv, Type,
 };
-use crate::syntax::{Const, ExprKind, Span};
+use crate::syntax::{Const, ExprKind, Label, Span};
 
 fn check_rectymerge(
     span: &Span,
@@ -58,15 +58,30 @@ fn typecheck_binop<'cx>(
             let x_type = l.ty();
             let y_type = r.ty();
 
+            let not_record_err = |operand: &Tir<'cx, '_>| {
+                mkerr(
+                    ErrorBuilder::new("MustCombineRecord")
+                        .span_err(
+                            operand.span(),
+                            format!(
+                                "expected a record, but this has type: `{}`",
+                                operand.ty().to_expr_tyenv(env)
+                            ),
+                        )
+                        .help("`⫽` can only combine records")
+                        .format(),
+                )
+            };
+
             // Extract the LHS record type
             let kts_x = match x_type.kind() {
                 RecordType(kts) => kts,
-                _ => return span_err("MustCombineRecord"),
+                _ => return not_record_err(&l),
             };
             // Extract the RHS record type
             let kts_y = match y_type.kind() {
                 RecordType(kts) => kts,
-                _ => return span_err("MustCombineRecord"),
+                _ => return not_record_err(&r),
             };
 
             // Union the two records, prefering
@@ -178,9 +193,11 @@ fn typecheck_merge<'cx>(
         _ => return span_err("Merge2ArgMustBeUnionOrOptional"),
     };
 
-    let mut inferred_type = None;
-    for (x, handler_type) in handlers {
-        let handler_return_type: Type = match variants.get(x) {
+    // Handlers are independent, except that they must agree on their return type.
+    let check_handler = |x: &Label,
+                         handler_type: &Nir<'cx>|
+     -> Result<Option<Type<'cx>>, TypeError> {
+        Ok(Some(match variants.get(x) {
             // Union alternative with type
             Some(Some(variant_type)) => match handler_type.kind() {
                 PiClosure { closure, annot, .. } => {
@@ -190,7 +207,7 @@ fn typecheck_merge<'cx>(
                                 "Wrong handler input type"
                             ))
                             .span_err(
-                                span,
+                                span.clone(),
                                 format!("in this merge expression",),
                             )
                             .span_err(
@@ -217,7 +234,12 @@ fn typecheck_merge<'cx>(
                     // TODO: this actually doesn't check anything yet
                     match closure.remove_binder() {
                         Some(v) => Type::new_infer_universe(env, v.clone())?,
-                        None => return span_err("MergeReturnTypeIsDependent"),
+                        None => {
+                            return mk_span_err(
+                                span.clone(),
+                                "MergeReturnTypeIsDependent",
+                            )
+                        }
                     }
                 }
                 _ => {
@@ -225,7 +247,10 @@ fn typecheck_merge<'cx>(
                         ErrorBuilder::new(format!(
                             "merge handler is not a function"
                         ))
-                        .span_err(span, format!("in this merge expression"))
+                        .span_err(
+                            span.clone(),
+                            format!("in this merge expression"),
+                        )
                         .span_err(
                             record.span(),
                             format!(
@@ -252,17 +277,50 @@ fn typecheck_merge<'cx>(
             },
             // Union alternative without type
             Some(None) => Type::new_infer_universe(env, handler_type.clone())?,
-            None => return span_err("MergeHandlerMissingVariant"),
+            None if env.options().allow_unused_handlers => {
+                env.warn(
+                    ErrorBuilder::new_warning(format!(
+                        "unused handler `{}`",
+                        x
+                    ))
+                    .span_warn(
+                        record.span(),
+                        format!("the union has no alternative `{}`", x),
+                    )
+                    .format(),
+                );
+                return Ok(None);
+            }
+            None => {
+                return mk_span_err(span.clone(), "MergeHandlerMissingVariant")
+            }
+        }))
+    };
+    let mut inferred_type = None;
+    let mut errors = Vec::new();
+    for (x, handler_type) in handlers {
+        let handler_return_type = match check_handler(x, handler_type) {
+            Ok(Some(t)) => t,
+            Ok(None) => continue,
+            Err(err) if env.collects_errors() => {
+                errors.push(err);
+                continue;
+            }
+            Err(err) => return Err(err),
         };
         match &inferred_type {
             None => inferred_type = Some(handler_return_type),
             Some(t) => {
                 if t != &handler_return_type {
-                    return span_err("MergeHandlerTypeMismatch");
+                    errors.extend(span_err("MergeHandlerTypeMismatch").err());
+                    break;
                 }
             }
         }
     }
+    if let Some(err) = env.report_errors(errors) {
+        return Err(err);
+    }
     for x in variants.keys() {
         if !handlers.contains_key(x) {
             return span_err("MergeVariantMissingHandler");
@@ -286,6 +344,27 @@ fn typecheck_merge<'cx>(
     })
 }
 
+/// When `f` is an application `g a b` whose type isn't a function, returns `g` and the number of
+/// arguments it takes. Applying `f` to one more argument is then an over-application, e.g.
+/// `Natural/even 2 3`.
+fn over_applied_head<'cx, 'hir>(
+    f: &'hir Hir<'cx>,
+) -> Option<(&'hir Hir<'cx>, usize)> {
+    let mut head = f;
+    let mut applied = 0;
+    while let HirKind::Expr(ExprKind::Op(OpKind::App(g, _))) = head.kind() {
+        head = g;
+        applied += 1;
+    }
+    // `f` typechecked, so `head` takes at least the arguments it is given. It takes no more,
+    // otherwise the type of `f` would be a function.
+    if applied == 0 {
+        None
+    } else {
+        Some((head, applied))
+    }
+}
+
 pub fn typecheck_operation<'cx>(
     env: &TyEnv<'cx>,
     span: Span,
@@ -332,17 +411,36 @@ pub fn typecheck_operation<'cx>(
                     let arg_nf = arg.eval(env);
                     Type::new_infer_universe(env, closure.apply(arg_nf))?
                 }
-                _ => return mkerr(
-                    ErrorBuilder::new(format!(
-                        "expected function, found `{}`",
-                        f.ty().to_expr_tyenv(env)
-                    ))
-                    .span_err(
-                        f.span(),
-                        format!("function application requires a function",),
-                    )
-                    .format(),
-                ),
+                _ => {
+                    if let Some((head, arity)) = over_applied_head(f.as_hir()) {
+                        return mkerr(
+                            ErrorBuilder::new(
+                                "function applied to too many arguments",
+                            )
+                            .span_err(
+                                head.span(),
+                                format!(
+                                    "this function takes {} argument{}",
+                                    arity,
+                                    if arity == 1 { "" } else { "s" },
+                                ),
+                            )
+                            .span_err(arg.span(), "extra argument")
+                            .format(),
+                        );
+                    }
+                    return mkerr(
+                        ErrorBuilder::new(format!(
+                            "expected function, found `{}`",
+                            f.ty().to_expr_tyenv(env)
+                        ))
+                        .span_err(
+                            f.span(),
+                            "function application requires a function",
+                        )
+                        .format(),
+                    );
+                }
             }
         }
         BinOp(o, l, r) => typecheck_binop(env, span, o, l, r)?,
@@ -0,0 +1,23 @@
+//! The types needed for common uses of the crate, so that `use dhall::prelude::*;` is enough to
+//! parse, resolve, typecheck and normalize an expression:
+//!
+//! ```
+//! use dhall::prelude::*;
+//!
+//! Ctxt::with_new(|cx| {
+//!     let typed = Parsed::parse_str("1 + 2")?.resolve(cx)?.typecheck(cx)?;
+//!     assert_eq!(typed.get_type()?.to_expr(cx).to_string(), "Natural");
+//!     assert_eq!(typed.normalize(cx).to_expr(cx).to_string(), "3");
+//!     Ok::<_, Error>(())
+//! })
+//! .unwrap();
+//! ```
+//!
+//! Everything here is also available under its full path.
+
+pub use crate::error::{Error, ErrorKind, TypeError};
+pub use crate::syntax::{Expr, ExprKind, Label, Span};
+pub use crate::{
+    Ctxt, NormalizeOptions, Normalized, Parsed, Resolved, ToExprOptions,
+    TypecheckOptions, Typed,
+};
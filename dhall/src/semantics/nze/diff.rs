@@ -0,0 +1,136 @@
+use std::collections::BTreeSet;
+
+use crate::error::Error;
+use crate::semantics::{Nir, NirKind};
+use crate::syntax::{Expr, ExprKind, Label};
+use crate::{Ctxt, Parsed};
+
+/// A step in the path from the root of a value to one of its leaves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    /// A record field.
+    Field(Label),
+    /// A list element.
+    Index(usize),
+}
+
+/// A leaf that differs between two values. `before` is `None` for added leaves and `after` is
+/// `None` for removed ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub path: Vec<PathSegment>,
+    pub before: Option<Expr>,
+    pub after: Option<Expr>,
+}
+
+fn normalize(cx: Ctxt<'_>, expr: &Expr) -> Result<Expr, Error> {
+    let typed = Parsed::from_expr_without_imports(expr.clone())
+        .skip_resolve(cx)?
+        .typecheck(cx)?;
+    Ok(typed.normalize(cx).to_expr(cx))
+}
+
+/// The elements of a list literal, or `None` if `expr` isn't one.
+fn list_elements(expr: &Expr) -> Option<&[Expr]> {
+    match expr.kind() {
+        ExprKind::EmptyListLit(_) => Some(&[]),
+        ExprKind::NEListLit(xs) => Some(xs.as_slice()),
+        _ => None,
+    }
+}
+
+fn diff(
+    path: &mut Vec<PathSegment>,
+    old: &Expr,
+    new: &Expr,
+    out: &mut Vec<Change>,
+) {
+    if old == new {
+        return;
+    }
+    match (old.kind(), new.kind()) {
+        (ExprKind::RecordLit(old_kvs), ExprKind::RecordLit(new_kvs)) => {
+            let labels: BTreeSet<_> =
+                old_kvs.keys().chain(new_kvs.keys()).collect();
+            for l in labels {
+                path.push(PathSegment::Field(l.clone()));
+                match (old_kvs.get(l), new_kvs.get(l)) {
+                    (Some(x), Some(y)) => diff(path, x, y, out),
+                    (x, y) => out.push(Change {
+                        path: path.clone(),
+                        before: x.cloned(),
+                        after: y.cloned(),
+                    }),
+                }
+                path.pop();
+            }
+        }
+        _ => match (list_elements(old), list_elements(new)) {
+            // Two empty lists only differ by their type, which is a leaf.
+            (Some(xs), Some(ys)) if !xs.is_empty() || !ys.is_empty() => {
+                for i in 0..xs.len().max(ys.len()) {
+                    path.push(PathSegment::Index(i));
+                    match (xs.get(i), ys.get(i)) {
+                        (Some(x), Some(y)) => diff(path, x, y, out),
+                        (x, y) => out.push(Change {
+                            path: path.clone(),
+                            before: x.cloned(),
+                            after: y.cloned(),
+                        }),
+                    }
+                    path.pop();
+                }
+            }
+            _ => out.push(Change {
+                path: path.clone(),
+                before: Some(old.clone()),
+                after: Some(new.clone()),
+            }),
+        },
+    }
+}
+
+/// Compares two closed expressions by value and returns the leaves that differ, looking through
+/// records and lists. Both expressions are typechecked and normalized first, so only differences
+/// in meaning are reported: reordering record fields or inlining a `let` is not a change.
+///
+/// List elements are compared by position, so inserting an element in the middle of a list shows
+/// up as a change of every following element.
+pub fn semantic_diff(
+    cx: Ctxt<'_>,
+    old: &Expr,
+    new: &Expr,
+) -> Result<Vec<Change>, Error> {
+    let old = normalize(cx, old)?;
+    let new = normalize(cx, new)?;
+    let mut out = Vec::new();
+    diff(&mut Vec::new(), &old, &new, &mut out);
+    Ok(out)
+}
+
+fn normalize_type<'cx>(cx: Ctxt<'cx>, expr: &Expr) -> Result<Nir<'cx>, Error> {
+    let typed = Parsed::from_expr_without_imports(expr.clone())
+        .skip_resolve(cx)?
+        .typecheck_type(cx)?;
+    Ok(typed.normalize(cx).as_nir().clone())
+}
+
+/// Whether the record type `sub` has at least the fields of the record type `sup`, with equivalent
+/// types. Returns `false` if either is not a record type.
+///
+/// This is a helper for checking schema compatibility by hand: Dhall itself has no subtyping, and
+/// the typechecker never uses this.
+pub fn is_subrecord(
+    cx: Ctxt<'_>,
+    sub: &Expr,
+    sup: &Expr,
+) -> Result<bool, Error> {
+    let sub = normalize_type(cx, sub)?;
+    let sup = normalize_type(cx, sup)?;
+    Ok(match (sub.kind(), sup.kind()) {
+        (NirKind::RecordType(sub_kts), NirKind::RecordType(sup_kts)) => sup_kts
+            .iter()
+            .all(|(l, t)| sub_kts.get(l).map_or(false, |sub_t| sub_t == t)),
+        _ => false,
+    })
+}
@@ -1,5 +1,5 @@
 use crate::semantics::{AlphaVar, Nir, NirKind};
-use crate::Ctxt;
+use crate::{Ctxt, NormalizeOptions};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NzVar {
@@ -20,6 +20,7 @@ enum EnvItem<'cx, T> {
 #[derive(Debug, Clone)]
 pub struct ValEnv<'cx, T> {
     cx: Ctxt<'cx>,
+    opts: NormalizeOptions,
     items: Vec<EnvItem<'cx, T>>,
 }
 
@@ -50,14 +51,21 @@ impl NzVar {
 
 impl<'cx, T: Clone> ValEnv<'cx, T> {
     pub fn new(cx: Ctxt<'cx>) -> Self {
+        Self::with_options(cx, NormalizeOptions::default())
+    }
+    pub fn with_options(cx: Ctxt<'cx>, opts: NormalizeOptions) -> Self {
         ValEnv {
             cx,
+            opts,
             items: Vec::new(),
         }
     }
     pub fn cx(&self) -> Ctxt<'cx> {
         self.cx
     }
+    pub fn options(&self) -> NormalizeOptions {
+        self.opts
+    }
     pub fn discard_types(&self) -> ValEnv<'cx, ()> {
         let items = self
             .items
@@ -67,7 +75,11 @@ impl<'cx, T: Clone> ValEnv<'cx, T> {
                 EnvItem::Replaced(val, _) => EnvItem::Replaced(val.clone(), ()),
             })
             .collect();
-        ValEnv { cx: self.cx, items }
+        ValEnv {
+            cx: self.cx,
+            opts: self.opts,
+            items,
+        }
     }
 
     pub fn insert_type(&self, ty: T) -> Self {
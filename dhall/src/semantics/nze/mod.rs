@@ -1,8 +1,10 @@
+pub mod diff;
 pub mod env;
 pub mod lazy;
 pub mod nir;
 pub mod normalize;
 pub mod var;
+pub use diff::*;
 pub use env::*;
 pub use nir::*;
 pub use normalize::*;
@@ -1,7 +1,9 @@
-use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
 use std::rc::Rc;
 
-use crate::builtins::{Builtin, BuiltinClosure};
+use crate::builtins::{Builtin, BuiltinClosure, CustomBuiltinClosure};
 use crate::operations::{BinOp, OpKind};
 use crate::semantics::nze::lazy;
 use crate::semantics::{
@@ -53,7 +55,7 @@ pub struct TextLit<'cx>(Vec<InterpolatedTextContents<Nir<'cx>>>);
 /// if we have the first constructor of the NF at all levels, we actually have the NF.
 /// In particular, this means that once we get a `NirKind`, it can be considered immutable, and
 /// we only need to recursively normalize its sub-`Nir`s to get to the NF.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub enum NirKind<'cx> {
     /// Closures
     LamClosure {
@@ -67,6 +69,7 @@ pub enum NirKind<'cx> {
         closure: Closure<'cx>,
     },
     AppliedBuiltin(BuiltinClosure<'cx>),
+    AppliedCustomBuiltin(CustomBuiltinClosure<'cx>),
 
     Var(NzVar),
     Const(Const),
@@ -154,6 +157,21 @@ impl<'cx> Nir<'cx> {
     pub fn to_expr_tyenv(&self, tyenv: &TyEnv<'cx>) -> Expr {
         self.to_hir(tyenv.as_varenv()).to_expr_tyenv(tyenv)
     }
+    /// A hash that agrees with equality: values that are equal up to alpha- and beta-equivalence
+    /// hash the same. This is the hash of the alpha-normalized normal form, so it normalizes the
+    /// value fully.
+    pub fn structural_hash(&self, cx: Ctxt<'cx>) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.to_expr(
+            cx,
+            ToExprOptions {
+                alpha: true,
+                ..Default::default()
+            },
+        )
+        .hash(&mut hasher);
+        hasher.finish()
+    }
 
     pub fn app(&self, v: Self) -> Self {
         Nir::from_kind(self.app_to_kind(v))
@@ -179,10 +197,11 @@ impl<'cx> Nir<'cx> {
         let hir = match self.kind() {
             NirKind::Var(v) => HirKind::Var(venv.lookup(*v)),
             NirKind::AppliedBuiltin(closure) => closure.to_hirkind(venv),
+            NirKind::AppliedCustomBuiltin(closure) => closure.to_hirkind(venv),
             self_kind => HirKind::Expr(match self_kind {
-                NirKind::Var(..) | NirKind::AppliedBuiltin(..) => {
-                    unreachable!()
-                }
+                NirKind::Var(..)
+                | NirKind::AppliedBuiltin(..)
+                | NirKind::AppliedCustomBuiltin(..) => unreachable!(),
                 NirKind::LamClosure {
                     binder,
                     annot,
@@ -412,14 +431,180 @@ impl<'cx> lazy::Eval<NirKind<'cx>> for Thunk<'cx> {
     }
 }
 
+/// Pairs of values already found equal during a comparison. Values are often shared, so without
+/// this, comparing two separately-built values with a lot of internal sharing would take
+/// exponential time.
+#[derive(Default)]
+pub(crate) struct ConvMemo<'cx> {
+    /// Holds on to the values, so their addresses can't be reused while the comparison lasts.
+    known: HashSet<(ByAddress<'cx>, ByAddress<'cx>)>,
+}
+
+/// A `Nir` compared and hashed by address.
+struct ByAddress<'cx>(Nir<'cx>);
+
+impl<'cx> Hash for ByAddress<'cx> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Rc::as_ptr(&(self.0).0).hash(state)
+    }
+}
+impl<'cx> std::cmp::PartialEq for ByAddress<'cx> {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&(self.0).0, &(other.0).0)
+    }
+}
+impl<'cx> std::cmp::Eq for ByAddress<'cx> {}
+
+impl<'cx> Nir<'cx> {
+    /// Compare two values for equality modulo alpha/beta-equivalence.
+    pub(crate) fn conv(&self, other: &Self, memo: &mut ConvMemo<'cx>) -> bool {
+        if Rc::ptr_eq(&self.0, &other.0) {
+            return true;
+        }
+        let pair = (ByAddress(self.clone()), ByAddress(other.clone()));
+        if memo.known.contains(&pair) {
+            return true;
+        }
+        let eq = self.kind().conv(other.kind(), memo);
+        if eq {
+            memo.known.insert(pair);
+        }
+        eq
+    }
+}
+
+impl<'cx> NirKind<'cx> {
+    fn conv(&self, other: &Self, memo: &mut ConvMemo<'cx>) -> bool {
+        use NirKind::*;
+        fn all_conv<'a, 'cx: 'a>(
+            xs: impl ExactSizeIterator<Item = &'a Nir<'cx>>,
+            ys: impl ExactSizeIterator<Item = &'a Nir<'cx>>,
+            memo: &mut ConvMemo<'cx>,
+        ) -> bool {
+            xs.len() == ys.len() && xs.zip(ys).all(|(x, y)| x.conv(y, memo))
+        }
+        fn conv_maps<'cx>(
+            x: &HashMap<Label, Nir<'cx>>,
+            y: &HashMap<Label, Nir<'cx>>,
+            memo: &mut ConvMemo<'cx>,
+        ) -> bool {
+            x.len() == y.len()
+                && x.iter().all(|(k, vx)| match y.get(k) {
+                    Some(vy) => vx.conv(vy, memo),
+                    None => false,
+                })
+        }
+        fn conv_opt_maps<'cx>(
+            x: &HashMap<Label, Option<Nir<'cx>>>,
+            y: &HashMap<Label, Option<Nir<'cx>>>,
+            memo: &mut ConvMemo<'cx>,
+        ) -> bool {
+            x.len() == y.len()
+                && x.iter().all(|(k, vx)| match (vx, y.get(k)) {
+                    (Some(vx), Some(Some(vy))) => vx.conv(vy, memo),
+                    (None, Some(None)) => true,
+                    _ => false,
+                })
+        }
+        match (self, other) {
+            (
+                LamClosure {
+                    binder: b1,
+                    annot: a1,
+                    closure: c1,
+                },
+                LamClosure {
+                    binder: b2,
+                    annot: a2,
+                    closure: c2,
+                },
+            )
+            | (
+                PiClosure {
+                    binder: b1,
+                    annot: a1,
+                    closure: c1,
+                },
+                PiClosure {
+                    binder: b2,
+                    annot: a2,
+                    closure: c2,
+                },
+            ) => b1 == b2 && a1.conv(a2, memo) && c1.conv(c2, memo),
+            (AppliedBuiltin(x), AppliedBuiltin(y)) => x.conv(y, memo),
+            (AppliedCustomBuiltin(x), AppliedCustomBuiltin(y)) => {
+                x.conv(y, memo)
+            }
+            (Var(x), Var(y)) => x == y,
+            (Const(x), Const(y)) => x == y,
+            (Num(x), Num(y)) => x == y,
+            (BuiltinType(x), BuiltinType(y)) => x == y,
+            (TextLit(x), TextLit(y)) => {
+                use InterpolatedTextContents::{Expr, Text};
+                x.0.len() == y.0.len()
+                    && x.iter().zip(y.iter()).all(|pair| match pair {
+                        (Text(x), Text(y)) => x == y,
+                        (Expr(x), Expr(y)) => x.conv(y, memo),
+                        _ => false,
+                    })
+            }
+            (EmptyOptionalLit(x), EmptyOptionalLit(y))
+            | (NEOptionalLit(x), NEOptionalLit(y))
+            | (OptionalType(x), OptionalType(y))
+            | (EmptyListLit(x), EmptyListLit(y))
+            | (ListType(x), ListType(y))
+            | (Assert(x), Assert(y)) => x.conv(y, memo),
+            (NEListLit(xs), NEListLit(ys)) => {
+                all_conv(xs.iter(), ys.iter(), memo)
+            }
+            (RecordLit(x), RecordLit(y)) | (RecordType(x), RecordType(y)) => {
+                conv_maps(x, y, memo)
+            }
+            (UnionConstructor(lx, x), UnionConstructor(ly, y)) => {
+                lx == ly && conv_opt_maps(x, y, memo)
+            }
+            (UnionLit(lx, vx, x), UnionLit(ly, vy, y)) => {
+                lx == ly && vx.conv(vy, memo) && conv_opt_maps(x, y, memo)
+            }
+            (UnionType(x), UnionType(y)) => conv_opt_maps(x, y, memo),
+            (Equivalence(x1, x2), Equivalence(y1, y2)) => {
+                x1.conv(y1, memo) && x2.conv(y2, memo)
+            }
+            (Op(x), Op(y)) => {
+                // Same operation, then the same operands in the same order.
+                let (mut xs, mut ys) = (Vec::new(), Vec::new());
+                let x = x.map_ref(|v| xs.push(v));
+                let y = y.map_ref(|v| ys.push(v));
+                x == y && all_conv(xs.into_iter(), ys.into_iter(), memo)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<'cx> Closure<'cx> {
+    fn conv(&self, other: &Self, memo: &mut ConvMemo<'cx>) -> bool {
+        // The variable is fresh, so the pairs found equal under it stay equal afterwards.
+        let v = NzVar::fresh();
+        self.apply_var(v).conv(&other.apply_var(v), memo)
+    }
+}
+
 /// Compare two values for equality modulo alpha/beta-equivalence.
 impl<'cx> std::cmp::PartialEq for Nir<'cx> {
     fn eq(&self, other: &Self) -> bool {
-        Rc::ptr_eq(&self.0, &other.0) || self.kind() == other.kind()
+        self.conv(other, &mut ConvMemo::default())
     }
 }
 impl<'cx> std::cmp::Eq for Nir<'cx> {}
 
+impl<'cx> std::cmp::PartialEq for NirKind<'cx> {
+    fn eq(&self, other: &Self) -> bool {
+        self.conv(other, &mut ConvMemo::default())
+    }
+}
+impl<'cx> std::cmp::Eq for NirKind<'cx> {}
+
 impl<'cx> std::cmp::PartialEq for Thunk<'cx> {
     fn eq(&self, _other: &Self) -> bool {
         unreachable!(
@@ -431,8 +616,7 @@ impl<'cx> std::cmp::Eq for Thunk<'cx> {}
 
 impl<'cx> std::cmp::PartialEq for Closure<'cx> {
     fn eq(&self, other: &Self) -> bool {
-        let v = NzVar::fresh();
-        self.apply_var(v) == other.apply_var(v)
+        self.conv(other, &mut ConvMemo::default())
     }
 }
 impl<'cx> std::cmp::Eq for Closure<'cx> {}
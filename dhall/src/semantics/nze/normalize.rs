@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 
+use crate::builtins::CustomBuiltinClosure;
 use crate::operations::{normalize_operation, OpKind};
 use crate::semantics::NzEnv;
 use crate::semantics::{Binder, Closure, Hir, HirKind, Nir, NirKind, TextLit};
@@ -9,6 +10,7 @@ pub fn apply_any<'cx>(f: &Nir<'cx>, a: Nir<'cx>) -> NirKind<'cx> {
     match f.kind() {
         NirKind::LamClosure { closure, .. } => closure.apply(a).kind().clone(),
         NirKind::AppliedBuiltin(closure) => closure.apply(a),
+        NirKind::AppliedCustomBuiltin(closure) => closure.apply(a),
         NirKind::UnionConstructor(l, kts) => {
             NirKind::UnionLit(l.clone(), a, kts.clone())
         }
@@ -16,6 +18,8 @@ pub fn apply_any<'cx>(f: &Nir<'cx>, a: Nir<'cx>) -> NirKind<'cx> {
     }
 }
 
+/// Puts the contents of a text literal in canonical form: interpolated text literals are spliced
+/// in, and adjacent text chunks are merged, so e.g. `"a${"b${y}"}c"` becomes `"ab${y}c"`.
 pub fn squash_textlit<'cx>(
     elts: impl Iterator<Item = InterpolatedTextContents<Nir<'cx>>>,
 ) -> Vec<InterpolatedTextContents<Nir<'cx>>> {
@@ -154,11 +158,15 @@ pub fn normalize_one_layer<'cx>(expr: ExprKind<Nir<'cx>>) -> NirKind<'cx> {
 /// Normalize Hir into WHNF
 pub fn normalize_hir<'cx>(env: &NzEnv<'cx>, hir: &Hir<'cx>) -> NirKind<'cx> {
     match hir.kind() {
+        HirKind::CustomBuiltin(name) => {
+            CustomBuiltinClosure::new(name.clone(), env.clone())
+        }
         HirKind::MissingVar(..) => unreachable!("ruled out by typechecking"),
         HirKind::Var(var) => env.lookup_val(*var),
         HirKind::Import(import) => {
-            let typed = env.cx()[import].unwrap_result();
-            normalize_hir(env, &typed.hir)
+            // Share the value of the import between all its uses instead of evaluating it again.
+            let cx = env.cx();
+            cx[import].unwrap_result().nir(cx).kind().clone()
         }
         HirKind::ImportAlternative(alt, left, right) => {
             let hir = if env.cx()[alt].unwrap_selected() {
@@ -41,6 +41,9 @@ impl NameEnv {
         self.names.pop();
     }
 
+    /// Converts `x@n` to the De Bruijn index of the binder it refers to, i.e. the number of
+    /// binders of any name between it and the variable. `x@n` is the `n`th binder named `x` going
+    /// outwards, so we must count positions among all binders before keeping only those named `x`.
     pub fn unlabel_var(&self, var: &V) -> Option<AlphaVar> {
         let V(name, idx) = var;
         let (idx, _) = self
@@ -52,6 +55,13 @@ impl NameEnv {
             .nth(*idx)?;
         Some(AlphaVar::new(idx))
     }
+    /// The number of binders named `x`. When `x@n` is unbound, `x@n` refers to the variable named
+    /// `x` bound outside the expression with index `n` minus that number.
+    pub fn count(&self, x: &Label) -> usize {
+        self.names.iter().filter(|n| *n == x).count()
+    }
+    /// The inverse of `unlabel_var`: the `n` of `x@n` is the number of binders named `x` that are
+    /// closer than the one `var` refers to.
     pub fn label_var(&self, var: AlphaVar) -> V {
         let name = &self.names[self.names.len() - 1 - var.idx()];
         let idx = self
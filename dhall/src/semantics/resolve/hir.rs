@@ -1,6 +1,6 @@
 use crate::error::TypeError;
 use crate::semantics::{type_with, typecheck, NameEnv, Nir, NzEnv, Tir, TyEnv};
-use crate::syntax::{Expr, ExprKind, Span, V};
+use crate::syntax::{Expr, ExprKind, Label, Span, V};
 use crate::{Ctxt, ImportAlternativeId, ImportId, ToExprOptions};
 
 /// Stores an alpha-normalized variable.
@@ -15,6 +15,8 @@ pub enum HirKind<'cx> {
     Var(AlphaVar),
     /// A variable that couldn't be resolved. Detected during resolution, but causes an error during typeck.
     MissingVar(V),
+    /// A free variable that names one of the custom builtins of the context.
+    CustomBuiltin(Label),
     /// An import. It must have been resolved after resolution.
     Import(ImportId<'cx>),
     /// An import alternative. It must have been decided after resolution.
@@ -60,15 +62,18 @@ impl<'cx> Hir<'cx> {
     }
     /// Converts a closed Hir expr back to the corresponding AST expression.
     pub fn to_expr_noopts(&self, cx: Ctxt<'cx>) -> Expr {
-        let opts = ToExprOptions { alpha: false };
+        let opts = ToExprOptions::default();
         self.to_expr(cx, opts)
     }
     pub fn to_expr_alpha(&self, cx: Ctxt<'cx>) -> Expr {
-        let opts = ToExprOptions { alpha: true };
+        let opts = ToExprOptions {
+            alpha: true,
+            ..Default::default()
+        };
         self.to_expr(cx, opts)
     }
     pub fn to_expr_tyenv(&self, env: &TyEnv<'cx>) -> Expr {
-        let opts = ToExprOptions { alpha: false };
+        let opts = ToExprOptions::default();
         let cx = env.cx();
         let mut env = env.as_nameenv().clone();
         hir_to_expr(cx, self, opts, &mut env)
@@ -99,6 +104,15 @@ impl<'cx> Hir<'cx> {
     }
 }
 
+/// The name given to binders at depth `level` with `ToExprOptions::numbered_names`.
+fn numbered_name(level: usize) -> Label {
+    if level == 0 {
+        "x".into()
+    } else {
+        format!("x{}", level).into()
+    }
+}
+
 fn hir_to_expr<'cx>(
     cx: Ctxt<'cx>,
     hir: &Hir<'cx>,
@@ -106,9 +120,20 @@ fn hir_to_expr<'cx>(
     env: &mut NameEnv,
 ) -> Expr {
     let kind = match hir.kind() {
+        HirKind::Var(v) if opts.alpha && opts.numbered_names => {
+            let level = env.as_varenv().size() - 1 - v.idx();
+            ExprKind::Var(V(numbered_name(level), 0))
+        }
         HirKind::Var(v) if opts.alpha => ExprKind::Var(V("_".into(), v.idx())),
         HirKind::Var(v) => ExprKind::Var(env.label_var(*v)),
         HirKind::MissingVar(v) => ExprKind::Var(v.clone()),
+        // Skip the binders of the same name, which are all renamed in alpha mode.
+        HirKind::CustomBuiltin(name) if opts.alpha => {
+            ExprKind::Var(V(name.clone(), 0))
+        }
+        HirKind::CustomBuiltin(name) => {
+            ExprKind::Var(V(name.clone(), env.count(name)))
+        }
         HirKind::Import(import) => {
             let typed = cx[import].unwrap_result();
             return hir_to_expr(cx, &typed.hir, opts, &mut NameEnv::new());
@@ -133,7 +158,20 @@ fn hir_to_expr<'cx>(
                 e
             });
 
+            let level = env.as_varenv().size();
             match e {
+                ExprKind::Lam(_, t, e) if opts.alpha && opts.numbered_names => {
+                    ExprKind::Lam(numbered_name(level), t, e)
+                }
+                ExprKind::Pi(_, t, e) if opts.alpha && opts.numbered_names => {
+                    ExprKind::Pi(numbered_name(level), t, e)
+                }
+                // Variables bound by a `let` are renamed too, so the binder must follow.
+                ExprKind::Let(_, t, v, e)
+                    if opts.alpha && opts.numbered_names =>
+                {
+                    ExprKind::Let(numbered_name(level), t, v, e)
+                }
                 ExprKind::Lam(_, t, e) if opts.alpha => {
                     ExprKind::Lam("_".into(), t, e)
                 }
@@ -1,6 +1,6 @@
 use itertools::Itertools;
 use std::borrow::Cow;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::path::{Path, PathBuf};
 use url::Url;
@@ -12,8 +12,8 @@ use crate::operations::{BinOp, OpKind};
 use crate::semantics::{mkerr, Hir, HirKind, ImportEnv, NameEnv, Type};
 use crate::syntax;
 use crate::syntax::{
-    Expr, ExprKind, FilePath, FilePrefix, Hash, ImportMode, ImportTarget, Span,
-    UnspannedExpr, URL,
+    Expr, ExprKind, FilePath, FilePrefix, Hash, ImportMode, ImportTarget,
+    Label, Span, UnspannedExpr, URL, V,
 };
 use crate::{
     Ctxt, ImportAlternativeId, ImportId, ImportResultId, Parsed, Resolved,
@@ -344,6 +344,10 @@ pub fn check_hash<'cx>(
 }
 
 /// Desugar the first level of the expression.
+///
+/// `T::r` becomes `(T.default ⫽ r) : T.Type`, so typechecking and normalization need no special
+/// case for it. Since `⫽` is shallow, a field of `r` replaces the corresponding default wholesale:
+/// to only override part of a nested record, that field must itself use a completion.
 fn desugar(expr: &Expr) -> Cow<'_, Expr> {
     match expr.kind() {
         ExprKind::Op(OpKind::Completion(ty, compl)) => {
@@ -447,6 +451,12 @@ fn traverse_accumulate<'cx>(
     let kind = match expr.kind() {
         ExprKind::Var(var) => match name_env.unlabel_var(&var) {
             Some(v) => HirKind::Var(v),
+            // A free variable that names a custom builtin refers to it.
+            None if cx.custom_builtins().contains(&var.0)
+                && var.1 == name_env.count(&var.0) =>
+            {
+                HirKind::CustomBuiltin(var.0.clone())
+            }
             None => HirKind::MissingVar(var.clone()),
         },
         ExprKind::Op(OpKind::BinOp(BinOp::ImportAlt, l, r)) => {
@@ -564,6 +574,133 @@ pub fn skip_resolve<'cx>(
     resolve(cx, parsed)
 }
 
+/// Whether `label` is bound anywhere in `expr`.
+fn binds_label(expr: &Expr, label: &Label) -> bool {
+    expr.kind()
+        .traverse_ref_maybe_binder(|l, e| {
+            if l == Some(label) || binds_label(e, label) {
+                Err(())
+            } else {
+                Ok(())
+            }
+        })
+        .is_err()
+}
+
+/// Typechecks an expression without resolving its imports. Each import is treated as an opaque
+/// value whose type is given by `import_types`, and is an error if absent from it. Returns the
+/// type of the expression; if it depends on the value of an import, that value shows up as a free
+/// variable.
+pub fn typecheck_with_import_types<'cx>(
+    cx: Ctxt<'cx>,
+    parsed: &Parsed,
+    import_types: &HashMap<syntax::Import<Expr>, Expr>,
+) -> Result<Expr, Error> {
+    let expr = &parsed.0;
+    // The imports are replaced by variables bound outside the expression. Pick a name that the
+    // expression doesn't bind so that they can't be captured.
+    let mut label = Label::from("_import");
+    let mut suffix = 0;
+    while binds_label(expr, &label) {
+        suffix += 1;
+        label = Label::from(format!("_import{}", suffix));
+    }
+
+    let mut holes: Vec<syntax::Import<Expr>> = Vec::new();
+    let body = expr.traverse_imports(&mut |import, span| {
+        let idx = match holes.iter().position(|i| i == import) {
+            Some(idx) => idx,
+            None if import_types.contains_key(import) => {
+                holes.push(import.clone());
+                holes.len() - 1
+            }
+            None => {
+                return mkerr(
+                    ErrorBuilder::new("unresolved import")
+                        .span_err(span, "no type was provided for this import")
+                        .format(),
+                )
+            }
+        };
+        Ok(Expr::new(ExprKind::Var(V(label.clone(), idx)), span))
+    })?;
+
+    // The first import found gets the innermost binder, i.e. the index 0.
+    let wrapped = holes.iter().fold(body, |body, import| {
+        Expr::new(
+            ExprKind::Lam(label.clone(), import_types[import].clone(), body),
+            Span::Artificial,
+        )
+    });
+    let ty = Parsed::from_expr_without_imports(wrapped)
+        .skip_resolve(cx)?
+        .typecheck(cx)?
+        .get_type()?
+        .to_expr(cx);
+
+    // Strip the binders we added.
+    let mut ty = ty;
+    for _ in 0..holes.len() {
+        ty = match ty.kind() {
+            ExprKind::Pi(_, _, body) => body.clone(),
+            _ => unreachable!("the type of a function is a pi type"),
+        };
+    }
+    Ok(ty)
+}
+
+/// A free variable of an expression passed to `normalize_in_context`.
+#[derive(Debug, Clone)]
+pub enum ContextItem {
+    /// A variable whose value is known. It gets replaced by that value.
+    Replaced(Expr),
+    /// A variable of which only the type is known. It is kept as is.
+    Kept(Expr),
+}
+
+/// Normalizes an expression with free variables, given in `context` from the outermost to the
+/// innermost. The variables whose value is known are replaced, so this partially evaluates the
+/// expression; the others must be given a type. The values and types may refer to the variables
+/// before them.
+pub fn normalize_in_context<'cx>(
+    cx: Ctxt<'cx>,
+    context: &[(Label, ContextItem)],
+    parsed: &Parsed,
+) -> Result<Expr, Error> {
+    // Bind the variables around the expression: `let` for known values and `λ` for the others.
+    let wrapped =
+        context
+            .iter()
+            .rev()
+            .fold(parsed.0.clone(), |body, (l, item)| {
+                let kind = match item {
+                    ContextItem::Replaced(v) => {
+                        ExprKind::Let(l.clone(), None, v.clone(), body)
+                    }
+                    ContextItem::Kept(t) => {
+                        ExprKind::Lam(l.clone(), t.clone(), body)
+                    }
+                };
+                Expr::new(kind, Span::Artificial)
+            });
+    let mut expr = Parsed(wrapped, parsed.1.clone())
+        .resolve(cx)?
+        .typecheck(cx)?
+        .normalize(cx)
+        .to_expr(cx);
+
+    // The `let`s are gone; strip the `λ`s we added.
+    for (_, item) in context {
+        if let ContextItem::Kept(_) = item {
+            expr = match expr.kind() {
+                ExprKind::Lam(_, _, body) => body.clone(),
+                _ => unreachable!("the normal form of a function is a lambda"),
+            };
+        }
+    }
+    Ok(expr)
+}
+
 impl Parsed {
     fn resolve_with_env<'cx>(
         self,
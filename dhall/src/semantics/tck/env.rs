@@ -1,6 +1,10 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::error::TypeError;
 use crate::semantics::{AlphaVar, NameEnv, Nir, NzEnv, NzVar, Type, ValEnv};
 use crate::syntax::Label;
-use crate::Ctxt;
+use crate::{Ctxt, TypecheckOptions};
 
 /// Environment for indexing variables.
 #[derive(Debug, Clone, Copy, Default)]
@@ -12,6 +16,12 @@ pub struct VarEnv {
 #[derive(Debug, Clone)]
 pub struct TyEnv<'cx> {
     cx: Ctxt<'cx>,
+    opts: TypecheckOptions,
+    /// Shared by all the environments derived from the same root.
+    warnings: Rc<RefCell<Vec<String>>>,
+    /// When collecting errors, the errors found in independent subexpressions in addition to the
+    /// one that is returned. Shared like `warnings`.
+    errors: Option<Rc<RefCell<Vec<TypeError>>>>,
     names: NameEnv,
     items: ValEnv<'cx, Type<'cx>>,
 }
@@ -42,15 +52,67 @@ impl VarEnv {
 
 impl<'cx> TyEnv<'cx> {
     pub fn new(cx: Ctxt<'cx>) -> Self {
+        Self::with_options(cx, TypecheckOptions::default())
+    }
+    pub fn with_options(cx: Ctxt<'cx>, opts: TypecheckOptions) -> Self {
         TyEnv {
             cx,
+            opts,
+            warnings: Default::default(),
+            errors: None,
             names: NameEnv::new(),
             items: ValEnv::new(cx),
         }
     }
+    /// An environment that asks to keep going after errors in independent subexpressions. See
+    /// `typecheck_collecting`.
+    pub fn collecting_errors(cx: Ctxt<'cx>) -> Self {
+        TyEnv {
+            errors: Some(Default::default()),
+            ..Self::new(cx)
+        }
+    }
+    /// The same environment, but that stops at the first error.
+    pub fn not_collecting_errors(&self) -> Self {
+        TyEnv {
+            errors: None,
+            ..self.clone()
+        }
+    }
     pub fn cx(&self) -> Ctxt<'cx> {
         self.cx
     }
+    pub fn options(&self) -> TypecheckOptions {
+        self.opts
+    }
+    /// Record a warning, for problems that `options()` told us to tolerate.
+    pub fn warn(&self, warning: String) {
+        self.warnings.borrow_mut().push(warning)
+    }
+    /// The warnings recorded so far, in order.
+    pub fn warnings(&self) -> Vec<String> {
+        self.warnings.borrow().clone()
+    }
+    pub fn collects_errors(&self) -> bool {
+        self.errors.is_some()
+    }
+    /// Given the errors of independent subexpressions, returns the first one and records the
+    /// others if we are collecting errors.
+    pub fn report_errors(&self, errors: Vec<TypeError>) -> Option<TypeError> {
+        let mut errors = errors.into_iter();
+        let first = errors.next()?;
+        if let Some(collected) = &self.errors {
+            collected.borrow_mut().extend(errors);
+        }
+        Some(first)
+    }
+    /// The errors recorded so far, in order, leaving none behind.
+    pub fn take_errors(&self) -> Vec<TypeError> {
+        match &self.errors {
+            Some(errors) => errors.take(),
+            None => Vec::new(),
+        }
+    }
     pub fn as_varenv(&self) -> VarEnv {
         self.names.as_varenv()
     }
@@ -64,6 +126,9 @@ impl<'cx> TyEnv<'cx> {
     pub fn insert_type(&self, x: &Label, ty: Type<'cx>) -> Self {
         TyEnv {
             cx: self.cx,
+            opts: self.opts,
+            warnings: self.warnings.clone(),
+            errors: self.errors.clone(),
             names: self.names.insert(x),
             items: self.items.insert_type(ty),
         }
@@ -71,6 +136,9 @@ impl<'cx> TyEnv<'cx> {
     pub fn insert_value(&self, x: &Label, e: Nir<'cx>, ty: Type<'cx>) -> Self {
         TyEnv {
             cx: self.cx,
+            opts: self.opts,
+            warnings: self.warnings.clone(),
+            errors: self.errors.clone(),
             names: self.names.insert(x),
             items: self.items.insert_value(e, ty),
         }
@@ -0,0 +1,239 @@
+use crate::error::Error;
+use crate::semantics::{typecheck, AlphaVar, Hir, HirKind, TyEnv};
+use crate::syntax::{Const, Expr, ExprKind, Label, Span, V};
+use crate::Ctxt;
+
+/// A binder that encloses the expression being looked at.
+enum Binder<'cx> {
+    /// A `λ` or `∀` binder, with the type of the bound variable.
+    Lam(Label, Hir<'cx>),
+    /// A `let` binder, with its optional annotation and its value.
+    Let(Label, Option<Hir<'cx>>, Hir<'cx>),
+}
+
+struct Linter<'cx> {
+    cx: Ctxt<'cx>,
+    /// The name of the type variable to introduce. It is not mentioned anywhere in the expression
+    /// so the generalized function prints without renamings.
+    tyvar: Label,
+    out: Vec<(Label, Span, Expr)>,
+}
+
+/// Whether `label` is used anywhere in `hir`, as a free variable or a binder.
+fn mentions_label(hir: &Hir<'_>, label: &Label) -> bool {
+    match hir.kind() {
+        HirKind::MissingVar(V(l, _)) | HirKind::CustomBuiltin(l) => l == label,
+        HirKind::ImportAlternative(_, l, r) => {
+            mentions_label(l, label) || mentions_label(r, label)
+        }
+        HirKind::Expr(kind) => kind
+            .traverse_ref_maybe_binder(|l, e| {
+                if l == Some(label) || mentions_label(e, label) {
+                    Err(())
+                } else {
+                    Ok(())
+                }
+            })
+            .is_err(),
+        HirKind::Var(_) | HirKind::Import(_) => false,
+    }
+}
+
+/// Adds `delta` to the indices of the variables of `hir` that are bound outside of it, ignoring
+/// the `cutoff` innermost binders outside of it. This makes room for `delta` new binders.
+fn shift<'cx>(hir: &Hir<'cx>, delta: usize, cutoff: usize) -> Hir<'cx> {
+    let kind = match hir.kind() {
+        HirKind::Var(v) if v.idx() >= cutoff => {
+            HirKind::Var(AlphaVar::new(v.idx() + delta))
+        }
+        HirKind::ImportAlternative(alt, l, r) => HirKind::ImportAlternative(
+            *alt,
+            shift(l, delta, cutoff),
+            shift(r, delta, cutoff),
+        ),
+        HirKind::Expr(kind) => {
+            HirKind::Expr(kind.map_ref_maybe_binder(|l, e| {
+                shift(e, delta, cutoff + l.is_some() as usize)
+            }))
+        }
+        kind => kind.clone(),
+    };
+    Hir::new(kind, hir.span())
+}
+
+fn artificial(kind: ExprKind<Hir<'_>>) -> Hir<'_> {
+    Hir::new(HirKind::Expr(kind), Span::Artificial)
+}
+
+impl<'cx> Linter<'cx> {
+    /// Wraps `hir` in the enclosing binders and typechecks the result. Returns the type of `hir`,
+    /// in the scope of the enclosing binders, or `None` if it doesn't typecheck.
+    fn type_in_scope(
+        &self,
+        scope: &[Binder<'cx>],
+        hir: Hir<'cx>,
+    ) -> Option<Expr> {
+        let mut lams = 0;
+        let closed = scope.iter().rev().fold(hir, |e, binder| match binder {
+            Binder::Lam(l, t) => {
+                lams += 1;
+                artificial(ExprKind::Lam(l.clone(), t.clone(), e))
+            }
+            Binder::Let(l, t, v) => {
+                artificial(ExprKind::Let(l.clone(), t.clone(), v.clone(), e))
+            }
+        });
+        let tir = typecheck(self.cx, &closed).ok()?;
+        let mut ty = tir.ty().to_expr_tyenv(&TyEnv::new(self.cx));
+        for _ in 0..lams {
+            ty = match ty.kind() {
+                ExprKind::Pi(_, _, body) => body.clone(),
+                _ => unreachable!("the type of a function is a pi type"),
+            };
+        }
+        Some(ty)
+    }
+
+    /// Looks for an argument type of the function `value` that can be replaced by a type
+    /// variable. Returns the suggested signature for the generalized function.
+    fn generalize(
+        &self,
+        scope: &mut Vec<Binder<'cx>>,
+        value: &Hir<'cx>,
+    ) -> Option<Expr> {
+        let mut args = Vec::new();
+        let mut body = value;
+        while let HirKind::Expr(ExprKind::Lam(l, t, b)) = body.kind() {
+            args.push((l, t));
+            body = b;
+        }
+        // The type of the `i`th argument is under the `i` previous ones, so moving it to the
+        // `j`th argument takes `j - i` more binders.
+        let same_type =
+            |i: usize, j: usize| shift(args[i].1, j - i, 0) == *args[j].1;
+
+        for (i, (_, candidate)) in args.iter().enumerate() {
+            // Types that are already variables are either polymorphic already or aliases.
+            if let HirKind::Var(_) = candidate.kind() {
+                continue;
+            }
+            // Only consider the first occurrence of each type.
+            if (0..i).any(|k| same_type(k, i)) {
+                continue;
+            }
+            // Only abstract over types, not over kinds or terms.
+            let depth = scope.len();
+            scope.extend(
+                args[..i]
+                    .iter()
+                    .map(|(l, t)| Binder::Lam((*l).clone(), (*t).clone())),
+            );
+            let kind = self.type_in_scope(scope, (*candidate).clone());
+            scope.truncate(depth);
+            match kind.as_ref().map(Expr::kind) {
+                Some(ExprKind::Const(Const::Type)) => {}
+                _ => continue,
+            }
+
+            // The type variable is bound outside of all the arguments, so the variables bound
+            // outside of the function are one binder further.
+            let generalized = args.iter().enumerate().rev().fold(
+                shift(body, 1, args.len()),
+                |b, (j, (l, t))| {
+                    let t = if j >= i && same_type(i, j) {
+                        Hir::new(
+                            HirKind::Var(AlphaVar::new(j)),
+                            Span::Artificial,
+                        )
+                    } else {
+                        shift(t, 1, j)
+                    };
+                    artificial(ExprKind::Lam((*l).clone(), t, b))
+                },
+            );
+            let generalized = artificial(ExprKind::Lam(
+                self.tyvar.clone(),
+                artificial(ExprKind::Const(Const::Type)),
+                generalized,
+            ));
+            if let Some(ty) = self.type_in_scope(scope, generalized) {
+                return Some(ty);
+            }
+        }
+        None
+    }
+
+    fn visit(&mut self, scope: &mut Vec<Binder<'cx>>, hir: &Hir<'cx>) {
+        let kind = match hir.kind() {
+            HirKind::Expr(kind) => kind,
+            HirKind::ImportAlternative(alt, left, right) => {
+                let hir = if self.cx[alt].unwrap_selected() {
+                    left
+                } else {
+                    right
+                };
+                return self.visit(scope, hir);
+            }
+            // Imports are linted on their own.
+            _ => return,
+        };
+        match kind {
+            ExprKind::Lam(l, t, body) | ExprKind::Pi(l, t, body) => {
+                self.visit(scope, t);
+                scope.push(Binder::Lam(l.clone(), t.clone()));
+                self.visit(scope, body);
+                scope.pop();
+            }
+            ExprKind::Let(l, t, v, body) => {
+                if let Some(t) = t {
+                    self.visit(scope, t);
+                }
+                self.visit(scope, v);
+                if let Some(ty) = self.generalize(scope, v) {
+                    self.out.push((l.clone(), hir.span(), ty));
+                }
+                scope.push(Binder::Let(l.clone(), t.clone(), v.clone()));
+                self.visit(scope, body);
+                scope.pop();
+            }
+            kind => {
+                let _: Result<_, ()> = kind.traverse_ref(|e| {
+                    self.visit(scope, e);
+                    Ok(())
+                });
+            }
+        }
+    }
+}
+
+/// Finds the `let`-bound functions that could be made polymorphic. This is advisory: the program
+/// is unchanged.
+///
+/// The heuristic considers each binding `let f = λ(x : T) → ...` whose value is a function (of
+/// possibly several arguments). For each argument type `T` that is a `Type` and isn't already a
+/// variable, it replaces `T` by a new type variable `a` in all the arguments annotated with `T`
+/// and typechecks `λ(a : Type) → λ(x : a) → ...` in the scope of the binding. If that succeeds,
+/// the body never relies on what `T` is, and the binding is reported along with the type of the
+/// generalized function. Only the first such `T` of each binding is reported.
+pub fn generalizable_lets<'cx>(
+    cx: Ctxt<'cx>,
+    hir: &Hir<'cx>,
+) -> Result<Vec<(Label, Span, Expr)>, Error> {
+    // Report type errors in the program itself rather than silently finding nothing.
+    typecheck(cx, hir)?;
+
+    let mut tyvar = Label::from("a");
+    let mut suffix = 0;
+    while mentions_label(hir, &tyvar) {
+        suffix += 1;
+        tyvar = Label::from(format!("a{}", suffix));
+    }
+
+    let mut linter = Linter {
+        cx,
+        tyvar,
+        out: Vec::new(),
+    };
+    linter.visit(&mut Vec::new(), hir);
+    Ok(linter.out)
+}
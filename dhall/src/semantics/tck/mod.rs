@@ -1,6 +1,8 @@
 pub mod env;
+pub mod generalize;
 pub mod tir;
 pub mod typecheck;
 pub use env::*;
+pub use generalize::*;
 pub use tir::*;
 pub use typecheck::*;
@@ -2,10 +2,12 @@ use std::cmp::max;
 
 use crate::builtins::{type_of_builtin, Builtin};
 use crate::error::{ErrorBuilder, TypeError, TypeMessage};
-use crate::operations::typecheck_operation;
+use crate::operations::{typecheck_operation, BinOp, OpKind};
 use crate::semantics::{Hir, HirKind, Nir, NirKind, Tir, TyEnv, Type};
-use crate::syntax::{Const, ExprKind, InterpolatedTextContents, NumKind, Span};
-use crate::Ctxt;
+use crate::syntax::{
+    Const, Expr, ExprKind, InterpolatedTextContents, Label, NumKind, Span, V,
+};
+use crate::{Ctxt, TypecheckOptions};
 
 fn function_check(a: Const, b: Const) -> Const {
     if b == Const::Type {
@@ -88,9 +90,26 @@ fn type_one_layer<'cx>(
                 .to_type(Const::Type)
         }
         ExprKind::EmptyListLit(t) => {
+            // There is no bare `[]` in the grammar: `[] : T` is parsed as `EmptyListLit(T)`, so
+            // the annotation is already the full type of the list and there is nothing to infer.
+            // An outer annotation like `([] : List T) : List T` is then checked as usual.
             let t = t.eval_to_type(env)?;
             match t.kind() {
                 NirKind::ListType(..) => {}
+                // Before `None`, empty optionals were written as empty lists.
+                NirKind::OptionalType(ty) => {
+                    return mkerr(
+                        ErrorBuilder::new("InvalidListType")
+                            .span_err(span.clone(), "InvalidListType")
+                            .help(format!(
+                                "`[] : Optional T` is the deprecated syntax \
+                                 for an empty optional; write `None {}` \
+                                 instead",
+                                ty.to_expr_tyenv(env)
+                            ))
+                            .format(),
+                    )
+                }
                 _ => return span_err("InvalidListType"),
             };
             t
@@ -173,6 +192,331 @@ fn type_one_layer<'cx>(
     })
 }
 
+/// Finds a reference to `name` that couldn't be resolved.
+fn find_missing_var(hir: &Hir<'_>, name: &Label) -> Option<Span> {
+    match hir.kind() {
+        HirKind::MissingVar(V(var, _)) if var == name => Some(hir.span()),
+        HirKind::ImportAlternative(_, left, right) => {
+            find_missing_var(left, name)
+                .or_else(|| find_missing_var(right, name))
+        }
+        HirKind::Expr(ekind) => ekind
+            .traverse_ref(|e| match find_missing_var(e, name) {
+                Some(span) => Err(span),
+                None => Ok(()),
+            })
+            .err(),
+        _ => None,
+    }
+}
+
+/// Whether a `let` binding seems to define a type, judging only by its syntax since its value may
+/// not typecheck.
+fn looks_like_type(annot: &Option<Hir<'_>>, val: &Hir<'_>) -> bool {
+    use ExprKind::{Const, Pi, RecordType, UnionType};
+    let is_sort = matches!(
+        annot.as_ref().map(|t| t.kind()),
+        Some(HirKind::Expr(Const(_)))
+    );
+    is_sort
+        || matches!(
+            val.kind(),
+            HirKind::Expr(UnionType(_) | RecordType(_) | Pi(..))
+        )
+}
+
+/// If `x : t` is the desugaring of a completion `T::r`, i.e. `(T.default ⫽ r) : T.Type`, returns
+/// `T` and `r`.
+fn as_completion<'a, 'cx>(
+    x: &'a Hir<'cx>,
+    t: &'a Hir<'cx>,
+) -> Option<(&'a Hir<'cx>, &'a Hir<'cx>)> {
+    use OpKind::Field;
+    match (x.kind(), t.kind()) {
+        (
+            HirKind::Expr(ExprKind::Op(OpKind::BinOp(
+                BinOp::RightBiasedRecordMerge,
+                d,
+                r,
+            ))),
+            HirKind::Expr(ExprKind::Op(Field(schema, ty_field))),
+        ) if ty_field.as_ref() == "Type" => match d.kind() {
+            HirKind::Expr(ExprKind::Op(Field(_, default)))
+                if default.as_ref() == "default" =>
+            {
+                Some((schema, r))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Explains the failure of a completion `schema::r` of type `ty` when `r` has a field that
+/// `ty` doesn't. The desugared form would only report a mismatch between two large types.
+fn completion_unknown_field<'cx>(
+    env: &TyEnv<'cx>,
+    schema: &Hir<'cx>,
+    r: &Hir<'cx>,
+    ty: &Type<'cx>,
+) -> Option<TypeError> {
+    // Errors in `r` were already reported when typechecking the whole completion.
+    let r_tir = type_with(&env.not_collecting_errors(), r, None).ok()?;
+    let field = match (r_tir.ty().kind(), ty.kind()) {
+        (NirKind::RecordType(fields), NirKind::RecordType(allowed)) => fields
+            .keys()
+            .filter(|l| !allowed.contains_key(*l))
+            .min()?
+            .clone(),
+        _ => return None,
+    };
+    let span = match r.kind() {
+        HirKind::Expr(ExprKind::RecordLit(kvs)) => {
+            kvs.get(&field).map_or_else(|| r.span(), |v| v.span())
+        }
+        _ => r.span(),
+    };
+    let schema = schema.to_expr_tyenv(env);
+    let msg = ErrorBuilder::new(format!(
+        "record completion for schema `{}` has no field `{}`",
+        schema, field
+    ))
+    .span_err(
+        span,
+        format!("`{}` is not a field of `{}.Type`", field, schema),
+    )
+    .format();
+    Some(TypeError::new(TypeMessage::Custom(msg)))
+}
+
+/// When a record's type `ty` differs from the `expected` record type, finds the first field
+/// that doesn't match, looking inside nested record literals. Returns where to point and what to
+/// say, since the full types can be too large to compare by eye.
+fn first_field_mismatch<'cx>(
+    env: &TyEnv<'cx>,
+    hir: &Hir<'cx>,
+    ty: &Nir<'cx>,
+    expected: &Nir<'cx>,
+) -> Option<(Span, String)> {
+    let (kts, expected_kts) = match (ty.kind(), expected.kind()) {
+        (NirKind::RecordType(kts), NirKind::RecordType(expected_kts)) => {
+            (kts, expected_kts)
+        }
+        _ => return None,
+    };
+    let mut labels: Vec<&Label> =
+        kts.keys().chain(expected_kts.keys()).collect();
+    labels.sort();
+    labels.dedup();
+    let l = labels
+        .into_iter()
+        .find(|l| kts.get(*l) != expected_kts.get(*l))?;
+    let field_hir = match hir.kind() {
+        HirKind::Expr(ExprKind::RecordLit(kvs)) => kvs.get(l),
+        _ => None,
+    };
+    Some(match (kts.get(l), expected_kts.get(l)) {
+        (Some(_), None) => (
+            field_hir.map_or_else(|| hir.span(), |x| x.span()),
+            format!("unexpected field `{}`", l),
+        ),
+        (None, Some(expected_t)) => (
+            hir.span(),
+            format!(
+                "missing field `{}` of type `{}`",
+                l,
+                expected_t.to_expr_tyenv(env)
+            ),
+        ),
+        (Some(t), Some(expected_t)) => {
+            let mismatch = || {
+                format!(
+                    "field `{}` has type `{}` but `{}` was expected",
+                    l,
+                    t.to_expr_tyenv(env),
+                    expected_t.to_expr_tyenv(env)
+                )
+            };
+            match field_hir {
+                Some(x) => first_field_mismatch(env, x, t, expected_t)
+                    .unwrap_or_else(|| (x.span(), mismatch())),
+                None => (hir.span(), mismatch()),
+            }
+        }
+        (None, None) => unreachable!(),
+    })
+}
+
+/// Typechecks the children of a record or list literal, which don't depend on each other. All of
+/// them are typechecked even if one fails, and the errors after the first are reported to `env`.
+fn type_independent_children<'cx, 'hir>(
+    env: &TyEnv<'cx>,
+    ekind: &'hir ExprKind<Hir<'cx>>,
+) -> Result<ExprKind<Tir<'cx, 'hir>>, TypeError> {
+    let mut errors = Vec::new();
+    let children = ekind.map_ref(|e| {
+        in_step(env, type_with(env, e, None), || describe_child(ekind, e))
+            .map_err(|err| errors.push(err))
+            .ok()
+    });
+    match env.report_errors(errors) {
+        Some(err) => Err(err),
+        None => Ok(children.map_ref(|tir| tir.clone().unwrap())),
+    }
+}
+
+/// If tracing is enabled, record in the error that it occurred inside `step`.
+fn in_step<T>(
+    env: &TyEnv<'_>,
+    res: Result<T, TypeError>,
+    step: impl FnOnce() -> String,
+) -> Result<T, TypeError> {
+    res.map_err(|err| {
+        if env.options().trace {
+            err.push_trace(step())
+        } else {
+            err
+        }
+    })
+}
+
+/// Describes which part of `ekind` the sub-expression `child` is, for error traces.
+fn describe_child<'cx>(ekind: &ExprKind<Hir<'cx>>, child: &Hir<'cx>) -> String {
+    let mut parts: Vec<(&Hir<'cx>, String)> = Vec::new();
+    match ekind {
+        ExprKind::TextLit(interpolated) => {
+            for contents in interpolated.iter() {
+                if let InterpolatedTextContents::Expr(x) = contents {
+                    parts.push((x, "TextLit interpolation".to_owned()));
+                }
+            }
+        }
+        ExprKind::SomeLit(x) => parts.push((x, "SomeLit".to_owned())),
+        ExprKind::EmptyListLit(t) => {
+            parts.push((t, "EmptyListLit type".to_owned()))
+        }
+        ExprKind::NEListLit(xs) => {
+            for (i, x) in xs.iter().enumerate() {
+                parts.push((x, format!("NEListLit element {}", i)));
+            }
+        }
+        ExprKind::RecordLit(kvs) => {
+            for (k, v) in kvs {
+                parts.push((v, format!("RecordLit field \"{}\"", k)));
+            }
+        }
+        ExprKind::RecordType(kts) => {
+            for (k, t) in kts {
+                parts.push((t, format!("RecordType field \"{}\"", k)));
+            }
+        }
+        ExprKind::UnionType(kts) => {
+            for (k, t) in kts {
+                if let Some(t) = t {
+                    parts.push((t, format!("UnionType variant \"{}\"", k)));
+                }
+            }
+        }
+        ExprKind::Assert(t) => parts.push((t, "Assert".to_owned())),
+        ExprKind::Op(OpKind::App(f, a)) => {
+            parts.push((f, "App function".to_owned()));
+            parts.push((a, "App arg".to_owned()));
+        }
+        ExprKind::Op(OpKind::BinOp(o, x, y)) => {
+            parts.push((x, format!("`{}` left operand", o)));
+            parts.push((y, format!("`{}` right operand", o)));
+        }
+        ExprKind::Op(OpKind::BoolIf(b, t, f)) => {
+            parts.push((b, "BoolIf condition".to_owned()));
+            parts.push((t, "BoolIf then-branch".to_owned()));
+            parts.push((f, "BoolIf else-branch".to_owned()));
+        }
+        ExprKind::Op(OpKind::Merge(x, y, t)) => {
+            parts.push((x, "Merge handlers".to_owned()));
+            parts.push((y, "Merge union".to_owned()));
+            if let Some(t) = t {
+                parts.push((t, "Merge type".to_owned()));
+            }
+        }
+        ExprKind::Op(OpKind::ToMap(x, t)) => {
+            parts.push((x, "ToMap record".to_owned()));
+            if let Some(t) = t {
+                parts.push((t, "ToMap type".to_owned()));
+            }
+        }
+        ExprKind::Op(OpKind::Field(x, l)) => {
+            parts.push((x, format!("Field \"{}\"", l)))
+        }
+        ExprKind::Op(OpKind::Projection(x, _)) => {
+            parts.push((x, "Projection".to_owned()))
+        }
+        ExprKind::Op(OpKind::ProjectionByExpr(x, t)) => {
+            parts.push((x, "ProjectionByExpr record".to_owned()));
+            parts.push((t, "ProjectionByExpr type".to_owned()));
+        }
+        ExprKind::Op(OpKind::Completion(t, r)) => {
+            parts.push((t, "Completion type".to_owned()));
+            parts.push((r, "Completion record".to_owned()));
+        }
+        ExprKind::Op(OpKind::With(x, _, y)) => {
+            parts.push((x, "With record".to_owned()));
+            parts.push((y, "With value".to_owned()));
+        }
+        _ => {}
+    }
+    parts
+        .into_iter()
+        .find(|(e, _)| std::ptr::eq(*e, child))
+        .map(|(_, desc)| desc)
+        .unwrap_or_else(|| "subexpression".to_owned())
+}
+
+/// Typechecks the value of a `let` binding, checking it against the annotation if any.
+fn type_let_value<'cx, 'hir>(
+    env: &TyEnv<'cx>,
+    binder: &Label,
+    annot: &'hir Option<Hir<'cx>>,
+    val: &'hir Hir<'cx>,
+) -> Result<Tir<'cx, 'hir>, TypeError> {
+    let val_annot = annot
+        .as_ref()
+        .map(|t| {
+            in_step(env, type_with(env, t, None), || {
+                format!("Let \"{}\" annotation", binder)
+            })?
+            .eval_to_type(env)
+        })
+        .transpose()?;
+    match type_with(env, val, val_annot) {
+        Ok(val) => Ok(val),
+        Err(err) => match find_missing_var(val, binder) {
+            // `let` is not recursive, so the binder is out of scope in its own definition.
+            Some(span) => mkerr(
+                ErrorBuilder::new(format!(
+                    "recursive `let` binding `{}`",
+                    binder
+                ))
+                .span_err(
+                    span,
+                    format!(
+                        "`{}` is not in scope in its own definition",
+                        binder
+                    ),
+                )
+                .help(if looks_like_type(annot, val) {
+                    "`let` bindings are not recursive; encode a recursive type by taking it as a parameter instead, e.g. `∀(Tree : Type) → ∀(Leaf : Natural → Tree) → ∀(Node : List Tree → Tree) → Tree`"
+                } else {
+                    "`let` bindings are not recursive; use a builtin like `Natural/fold` or `List/fold` to iterate instead"
+                })
+                .format(),
+            ),
+            None => in_step(env, Err(err), || {
+                format!("Let \"{}\" value", binder)
+            }),
+        },
+    }
+}
+
 /// `type_with` typechecks an expression in the provided environment. Optionally pass an annotation
 /// to compare with.
 // We pass the annotation to avoid duplicating the annot checking logic. I hope one day we can use
@@ -184,6 +528,9 @@ pub fn type_with<'cx, 'hir>(
 ) -> Result<Tir<'cx, 'hir>, TypeError> {
     let tir = match hir.kind() {
         HirKind::Var(var) => Tir::from_hir(hir, env.lookup(*var)),
+        HirKind::CustomBuiltin(name) => {
+            Tir::from_hir(hir, env.cx().custom_builtin_type(name).clone())
+        }
         HirKind::MissingVar(var) => mkerr(
             ErrorBuilder::new(format!("unbound variable `{}`", var))
                 .span_err(hir.span(), "not found in this scope")
@@ -194,6 +541,9 @@ pub fn type_with<'cx, 'hir>(
             Tir::from_hir(hir, typed.ty.clone())
         }
         HirKind::ImportAlternative(alt, left, right) => {
+            // The standard resolves `a ? b` to whichever alternative resolves first, and the other
+            // one is discarded without being typechecked. So the two sides may well have different
+            // types, e.g. in `./config.dhall ? "fallback"`, and we must not reject that.
             let hir = if env.cx()[alt].unwrap_selected() {
                 left
             } else {
@@ -205,23 +555,47 @@ pub fn type_with<'cx, 'hir>(
             unreachable!("Hir should contain no unresolved variables")
         }
         HirKind::Expr(ExprKind::Const(Const::Sort)) => {
-            return mk_span_err(hir.span(), "Sort does not have a type")
+            return mkerr(
+                ErrorBuilder::new("Sort does not have a type")
+                    .span_err(hir.span(), "Sort does not have a type")
+                    .help(
+                        "`Sort` is the type of kinds like `Kind`, and has no type of its own",
+                    )
+                    .format(),
+            )
         }
-        HirKind::Expr(ExprKind::Annot(x, t)) => {
-            let t = match t.kind() {
+        HirKind::Expr(ExprKind::Annot(x, t_hir)) => {
+            let t = match t_hir.kind() {
                 HirKind::Expr(ExprKind::Const(Const::Sort)) => {
                     Type::from_const(Const::Sort)
                 }
-                _ => type_with(env, t, None)?.eval_to_type(env)?,
+                _ => in_step(env, type_with(env, t_hir, None), || {
+                    "Annot type".to_string()
+                })?
+                .eval_to_type(env)?,
             };
-            type_with(env, x, Some(t))?
+            let res = match (
+                type_with(env, x, Some(t.clone())),
+                as_completion(x, t_hir),
+            ) {
+                (Err(err), Some((schema, r))) => {
+                    Err(completion_unknown_field(env, schema, r, &t)
+                        .unwrap_or(err))
+                }
+                (res, _) => res,
+            };
+            in_step(env, res, || "Annot value".to_string())?
         }
 
         HirKind::Expr(ExprKind::Lam(binder, annot, body)) => {
-            let annot = type_with(env, annot, None)?;
+            let annot = in_step(env, type_with(env, annot, None), || {
+                format!("Lam \"{}\" annotation", binder)
+            })?;
             let annot_nf = annot.eval_to_type(env)?;
             let body_env = env.insert_type(binder, annot_nf);
-            let body = type_with(&body_env, body, None)?;
+            let body = in_step(env, type_with(&body_env, body, None), || {
+                format!("Lam \"{}\" body", binder)
+            })?;
 
             let u_annot = annot.ty().as_const().unwrap();
             let u_body = match body.ty().ty().as_const() {
@@ -242,10 +616,14 @@ pub fn type_with<'cx, 'hir>(
             Tir::from_hir(hir, ty)
         }
         HirKind::Expr(ExprKind::Pi(binder, annot, body)) => {
-            let annot = type_with(env, annot, None)?;
+            let annot = in_step(env, type_with(env, annot, None), || {
+                format!("Pi \"{}\" annotation", binder)
+            })?;
             let annot_val = annot.eval_to_type(env)?;
             let body_env = env.insert_type(binder, annot_val);
-            let body = type_with(&body_env, body, None)?;
+            let body = in_step(env, type_with(&body_env, body, None), || {
+                format!("Pi \"{}\" body", binder)
+            })?;
             body.ensure_is_type(env)?;
 
             let ks = annot.ty().as_const().unwrap();
@@ -254,19 +632,26 @@ pub fn type_with<'cx, 'hir>(
             Tir::from_hir(hir, ty)
         }
         HirKind::Expr(ExprKind::Let(binder, annot, val, body)) => {
-            let val_annot = annot
-                .as_ref()
-                .map(|t| type_with(env, t, None)?.eval_to_type(env))
-                .transpose()?;
-            let val = type_with(env, &val, val_annot)?;
+            let val = type_let_value(env, binder, annot, val)?;
             let val_nf = val.eval(env);
             let body_env = env.insert_value(&binder, val_nf, val.ty().clone());
-            let body = type_with(&body_env, body, None)?;
+            let body = in_step(env, type_with(&body_env, body, None), || {
+                format!("Let \"{}\" body", binder)
+            })?;
             let ty = body.ty().clone();
             Tir::from_hir(hir, ty)
         }
+        HirKind::Expr(
+            ekind @ (ExprKind::RecordLit(_) | ExprKind::NEListLit(_)),
+        ) if env.collects_errors() => {
+            let ekind = type_independent_children(env, ekind)?;
+            let ty = type_one_layer(env, ekind, hir.span())?;
+            Tir::from_hir(hir, ty)
+        }
         HirKind::Expr(ekind) => {
-            let ekind = ekind.traverse_ref(|e| type_with(env, e, None))?;
+            let ekind = ekind.traverse_ref(|e| {
+                in_step(env, type_with(env, e, None), || describe_child(ekind, e))
+            })?;
             let ty = type_one_layer(env, ekind, hir.span())?;
             Tir::from_hir(hir, ty)
         }
@@ -274,14 +659,49 @@ pub fn type_with<'cx, 'hir>(
 
     if let Some(annot) = annot {
         if *tir.ty() != annot {
-            return mk_span_err(
-                hir.span(),
-                &format!(
-                    "annot mismatch: {} != {}",
-                    tir.ty().to_expr_tyenv(env),
-                    annot.to_expr_tyenv(env)
-                ),
+            let msg = format!(
+                "annot mismatch: {} != {}",
+                tir.ty().to_expr_tyenv(env),
+                annot.to_expr_tyenv(env)
             );
+            let mut err = ErrorBuilder::new(&msg);
+            match first_field_mismatch(
+                env,
+                hir,
+                tir.ty().as_nir(),
+                annot.as_nir(),
+            ) {
+                Some((span, field_msg)) => err.span_err(span, field_msg),
+                None => err.span_err(hir.span(), &msg),
+            };
+            // Annotating with a universe is a common source of confusion.
+            match annot.as_const() {
+                Some(Const::Type) => err.help(
+                    "only types like `Natural` or `List Bool` have type `Type`",
+                ),
+                Some(Const::Kind) => err.help(
+                    "only type-level expressions like `Type` or `Type → Type` have type `Kind`",
+                ),
+                Some(Const::Sort) => err.help(
+                    "only kind-level expressions like `Kind` or `Type → Kind` have type `Sort`",
+                ),
+                None => &mut err,
+            };
+            // Before `Some`, optional values were written as one-element lists.
+            if let (
+                NirKind::OptionalType(_),
+                HirKind::Expr(ExprKind::NEListLit(xs)),
+            ) = (annot.kind(), hir.kind())
+            {
+                if xs.len() == 1 {
+                    err.help(format!(
+                        "`[ x ] : Optional T` is the deprecated syntax for an \
+                         optional value; write `Some {}` instead",
+                        xs[0].to_expr_tyenv(env)
+                    ));
+                }
+            }
+            return mkerr(err.format());
         }
     }
 
@@ -297,6 +717,66 @@ pub fn typecheck<'cx, 'hir>(
     type_with(&TyEnv::new(cx), hir, None)
 }
 
+/// Typechecks the `let` bindings at the top of the expression, e.g. `x` and `y` in
+/// `let x = 1 let y = x + 1 in y`, and returns the name and normalized type of each, in order.
+/// The body of the last `let` is not typechecked.
+pub fn binding_types<'cx>(
+    cx: Ctxt<'cx>,
+    hir: &Hir<'cx>,
+) -> Result<Vec<(Label, Expr)>, TypeError> {
+    let mut env = TyEnv::new(cx);
+    let mut hir = hir;
+    let mut types = Vec::new();
+    while let HirKind::Expr(ExprKind::Let(binder, annot, val, body)) =
+        hir.kind()
+    {
+        let val = type_let_value(&env, binder, annot, val)?;
+        types.push((binder.clone(), val.ty().to_expr_tyenv(&env)));
+        let val_nf = val.eval(&env);
+        env = env.insert_value(binder, val_nf, val.ty().clone());
+        hir = body;
+    }
+    Ok(types)
+}
+
+/// Like `typecheck`, but with the provided options.
+pub fn typecheck_with_options<'cx, 'hir>(
+    cx: Ctxt<'cx>,
+    hir: &'hir Hir<'cx>,
+    opts: TypecheckOptions,
+) -> Result<Tir<'cx, 'hir>, TypeError> {
+    type_with(&TyEnv::with_options(cx, opts), hir, None)
+}
+
+/// Like `typecheck_with_options`, but also returns the warnings about problems that `opts` told us
+/// to tolerate.
+pub fn typecheck_with_warnings<'cx, 'hir>(
+    cx: Ctxt<'cx>,
+    hir: &'hir Hir<'cx>,
+    opts: TypecheckOptions,
+) -> Result<(Tir<'cx, 'hir>, Vec<String>), TypeError> {
+    let env = TyEnv::with_options(cx, opts);
+    let tir = type_with(&env, hir, None)?;
+    Ok((tir, env.warnings()))
+}
+
+/// Like `typecheck`, but keeps going after an error in an independent part of the expression, i.e.
+/// a field of a record literal, an element of a list literal or a handler of a `merge`. Returns
+/// all the errors found, starting with the one `typecheck` would have returned. Errors that make
+/// the rest of the expression meaningless, like a mismatch between list elements, still stop
+/// typechecking.
+pub fn typecheck_collecting<'cx, 'hir>(
+    cx: Ctxt<'cx>,
+    hir: &'hir Hir<'cx>,
+) -> Result<Tir<'cx, 'hir>, Vec<TypeError>> {
+    let env = TyEnv::collecting_errors(cx);
+    type_with(&env, hir, None).map_err(|err| {
+        let mut errors = vec![err];
+        errors.extend(env.take_errors());
+        errors
+    })
+}
+
 /// Like `typecheck`, but additionally checks that the expression's type matches the provided type.
 pub fn typecheck_with<'cx, 'hir>(
     cx: Ctxt<'cx>,
@@ -1,22 +1,30 @@
-use std::collections::BTreeMap;
+use num_bigint::{BigInt, BigUint};
+use num_traits::ToPrimitive;
+use std::collections::{BTreeMap, HashMap};
 
 use crate::builtins::Builtin;
-use crate::error::Error;
+use crate::error::{Error, NotALiteral};
 use crate::operations::OpKind;
 use crate::semantics::Universe;
 use crate::syntax::visitor;
 use crate::syntax::*;
 
-pub type Integer = i64;
-pub type Natural = u64;
+pub type Integer = BigInt;
+pub type Natural = BigUint;
 pub type Double = NaiveDouble;
 
-/// Double with bitwise equality
+/// Double with bitwise equality, except that all `NaN`s are equal.
+///
+/// This matches the standard, which compares doubles through their binary encoding: `+0.0` and
+/// `-0.0` are distinct, and `NaN` has a single canonical encoding. The ordering agrees with this
+/// equality: `-0.0 < +0.0`, and `NaN` is greater than every other value.
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NaiveDouble(f64);
 
 /// Constants for a pure type system
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Const {
     Type,
     Kind,
@@ -35,6 +43,7 @@ impl Const {
 /// The `Int` field is a DeBruijn index.
 /// See dhall-lang/standard/semantics.md for details
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct V(pub Label, pub usize);
 
 // Each node carries an annotation.
@@ -48,6 +57,7 @@ pub type UnspannedExpr = ExprKind<Expr>;
 
 /// Numeric literals
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NumKind {
     ///  `True`
     Bool(bool),
@@ -64,6 +74,7 @@ pub enum NumKind {
 // much more generic code and improves pattern-matching behind
 // smart pointers.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ExprKind<SubExpr> {
     /// `Type`, `Kind` and `Sort`
     Const(Const),
@@ -159,6 +170,24 @@ impl<SE> ExprKind<SE> {
     }
 }
 
+/// Only the contents of the expression are serialized; spans are dropped.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Expr {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        self.kind().serialize(s)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Expr {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        d: D,
+    ) -> Result<Self, D::Error> {
+        let kind = UnspannedExpr::deserialize(d)?;
+        Ok(Expr::new(kind, Span::Artificial))
+    }
+}
+
 impl Expr {
     pub fn as_ref(&self) -> &UnspannedExpr {
         &self.kind
@@ -169,6 +198,9 @@ impl Expr {
     pub fn span(&self) -> Span {
         self.span.clone()
     }
+    pub(crate) fn span_ref(&self) -> &Span {
+        &self.span
+    }
 
     pub fn new(kind: UnspannedExpr, span: Span) -> Self {
         Expr {
@@ -183,10 +215,186 @@ impl Expr {
         Ok(crate::utils::sha256_hash(&data))
     }
 
+    /// A `Text` literal containing exactly `s`, without interpolation. Escaping is taken care
+    /// of when printing, so the printed expression parses back to the same string.
+    pub fn text_literal(s: &str) -> Expr {
+        Expr::new(ExprKind::TextLit(s.to_owned().into()), Span::Artificial)
+    }
+
     /// Wrap the expression into an additional let-binding
     pub fn add_let_binding(self, label: Label, value: Expr) -> Expr {
         Expr::new(ExprKind::Let(label, None, value, self), Span::Artificial)
     }
+
+    /// Find the binders that shadow an outer binder of the same name, i.e. after which the outer
+    /// variable can only be referred to as `x@1`. Returns the name and the span of each shadowing
+    /// `λ`, `∀` or `let`.
+    ///
+    /// Shadowing is often intentional, so binders whose name starts with `_` (including the `_`
+    /// of `A → B`) are never reported.
+    pub fn shadowed_bindings(&self) -> Vec<(Label, Span)> {
+        fn go(e: &Expr, scope: &mut Vec<Label>, out: &mut Vec<(Label, Span)>) {
+            use ExprKind::{Lam, Let, Pi};
+            if let Lam(l, _, _) | Pi(l, _, _) | Let(l, _, _, _) = e.kind() {
+                if !l.as_ref().starts_with('_') && scope.contains(l) {
+                    out.push((l.clone(), e.span()));
+                }
+            }
+            let _: Result<_, X> = e.kind().traverse_ref_maybe_binder(|l, e| {
+                match l {
+                    Some(l) => {
+                        scope.push(l.clone());
+                        go(e, scope, out);
+                        scope.pop();
+                    }
+                    None => go(e, scope, out),
+                }
+                Ok(())
+            });
+        }
+        let mut out = Vec::new();
+        go(self, &mut Vec::new(), &mut out);
+        out
+    }
+
+    /// Collect all the imports in the expression, in the order they appear. This includes the
+    /// imports used in the headers of remote imports.
+    pub fn imports(&self) -> Vec<&Import<Expr>> {
+        fn go<'a>(e: &'a Expr, out: &mut Vec<&'a Import<Expr>>) {
+            if let ExprKind::Import(import) = e.kind() {
+                out.push(import);
+            }
+            let _: Result<_, X> = e.kind().traverse_ref(|e| {
+                go(e, out);
+                Ok(())
+            });
+        }
+        let mut out = Vec::new();
+        go(self, &mut out);
+        out
+    }
+
+    /// Replace every import in the expression with the result of `f`. The results are not
+    /// traversed further. This is the building block for resolving imports in a custom way, e.g.
+    /// from an in-memory store.
+    pub fn traverse_imports<Err>(
+        &self,
+        f: &mut impl FnMut(&Import<Expr>, Span) -> Result<Expr, Err>,
+    ) -> Result<Expr, Err> {
+        match self.kind() {
+            ExprKind::Import(import) => f(import, self.span()),
+            kind => Ok(Expr::new(
+                kind.traverse_ref(|e| e.traverse_imports(f))?,
+                self.span(),
+            )),
+        }
+    }
+
+    /// Replace each `env:VAR` import whose variable is in `overrides` with the corresponding
+    /// expression, e.g. to inject values in tests without touching the real environment. Other
+    /// imports, including `env:VAR as Text` and `env:VAR as Location`, are left for the resolver.
+    pub fn with_env_overrides(
+        &self,
+        overrides: &HashMap<String, Expr>,
+    ) -> Expr {
+        let res: Result<_, X> = self.traverse_imports(&mut |import, span| {
+            if let (ImportMode::Code, ImportTarget::Env(var)) =
+                (&import.mode, &import.location)
+            {
+                if let Some(e) = overrides.get(var) {
+                    return Ok(e.clone());
+                }
+            }
+            Ok(Expr::new(ExprKind::Import(import.clone()), span))
+        });
+        match res {
+            Ok(e) => e,
+            Err(x) => match x {},
+        }
+    }
+
+    /// Replace the free occurrences of the variable `name` (i.e. `name@0`) with `value`, as
+    /// `let name = value in self` would. The free variables of `value` are shifted under binders
+    /// so they don't get captured, and the other free variables `name@n` are renumbered to
+    /// `name@(n-1)`. This does not normalize anything.
+    pub fn substitute_name(&self, name: &Label, value: &Expr) -> Expr {
+        let var = V(name.clone(), 0);
+        self.subst(&var, &value.shift(1, &var)).shift(-1, &var)
+    }
+
+    /// Follow `path` through nested record literals and return the field it leads to, e.g.
+    /// `["a", "b"]` gives `1` in `{ a = { b = 1 } }`. Returns `None` if a field is missing or if
+    /// an intermediate expression isn't a record literal. This is purely syntactic: nothing is
+    /// normalized, so fields of a `let`-bound or merged record are not found.
+    pub fn get_path(&self, path: &[Label]) -> Option<&Expr> {
+        match path.split_first() {
+            None => Some(self),
+            Some((l, rest)) => match self.kind() {
+                ExprKind::RecordLit(kvs) => kvs.get(l)?.get_path(rest),
+                _ => None,
+            },
+        }
+    }
+
+    /// Fold the expression bottom-up: `f` is called on each node once its subexpressions have
+    /// been folded, children first. This computes summaries like the size or depth of an
+    /// expression without writing the recursion by hand.
+    ///
+    /// Binders are not treated specially: `λ`, `∀` and `let` nodes reach `f` as
+    /// `ExprKind::Lam(label, a, b)`, `Pi(label, a, b)` and `Let(label, a, b, c)`, and the results
+    /// for their bodies were computed without knowing that they are in the scope of `label`.
+    /// Variables reach `f` as `Var(V(name, index))`, so `f` can tell them apart by their index.
+    /// To carry scope information down instead, recurse with `traverse_ref_maybe_binder`.
+    pub fn cata<A>(&self, f: &mut impl FnMut(ExprKind<A>) -> A) -> A {
+        let kind = self.kind().map_ref(|e| e.cata(f));
+        f(kind)
+    }
+
+    /// Build an expression top-down from a seed: `f` turns a seed into a node whose
+    /// subexpressions are seeds, which are then unfolded in turn. This is the inverse of `cata`.
+    /// The resulting expression has no spans.
+    pub fn ana<S: Clone>(
+        seed: S,
+        f: &mut impl FnMut(S) -> ExprKind<S>,
+    ) -> Expr {
+        let kind = f(seed).map_ref(|s| Expr::ana(s.clone(), f));
+        Expr::new(kind, Span::Artificial)
+    }
+
+    /// Add `delta` to the index of the free variables named like `var` whose index is at least
+    /// that of `var`.
+    fn shift(&self, delta: isize, var: &V) -> Expr {
+        let V(x, m) = var;
+        let kind = match self.kind() {
+            ExprKind::Var(V(y, n)) if y == x && n >= m => {
+                ExprKind::Var(V(y.clone(), (*n as isize + delta) as usize))
+            }
+            kind => kind.map_ref_maybe_binder(|l, e| match l {
+                Some(l) if l == x => e.shift(delta, &V(x.clone(), m + 1)),
+                _ => e.shift(delta, var),
+            }),
+        };
+        Expr::new(kind, self.span())
+    }
+
+    /// Replace the free variable `var` with `value`, without removing the binder.
+    fn subst(&self, var: &V, value: &Expr) -> Expr {
+        match self.kind() {
+            ExprKind::Var(v) if v == var => value.clone(),
+            kind => Expr::new(
+                kind.map_ref_maybe_binder(|l, e| match l {
+                    Some(l) => {
+                        let V(x, n) = var;
+                        let n = if l == x { n + 1 } else { *n };
+                        let value = value.shift(1, &V(l.clone(), 0));
+                        e.subst(&V(x.clone(), n), &value)
+                    }
+                    None => e.subst(var, value),
+                }),
+                self.span(),
+            ),
+        }
+    }
 }
 
 // Empty enum to indicate that no error can occur
@@ -198,20 +406,51 @@ pub(crate) fn trivial_result<T>(x: Result<T, X>) -> T {
     }
 }
 
+impl NaiveDouble {
+    fn canonical(self) -> f64 {
+        if self.0.is_nan() {
+            f64::NAN
+        } else {
+            self.0
+        }
+    }
+    fn canonical_bits(self) -> u64 {
+        self.canonical().to_bits()
+    }
+}
+
 impl PartialEq for NaiveDouble {
     fn eq(&self, other: &Self) -> bool {
-        self.0.to_bits() == other.0.to_bits()
+        self.canonical_bits() == other.canonical_bits()
     }
 }
 
 impl Eq for NaiveDouble {}
 
+impl PartialOrd for NaiveDouble {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for NaiveDouble {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // Same as `f64::total_cmp`, which is too recent for our minimum supported Rust version:
+        // flipping the bits of the magnitude of negative numbers orders them as integers.
+        let key = |x: &NaiveDouble| {
+            let bits = x.canonical_bits() as i64;
+            bits ^ (((bits >> 63) as u64) >> 1) as i64
+        };
+        key(self).cmp(&key(other))
+    }
+}
+
 impl std::hash::Hash for NaiveDouble {
     fn hash<H>(&self, state: &mut H)
     where
         H: std::hash::Hasher,
     {
-        self.0.to_bits().hash(state)
+        self.canonical_bits().hash(state)
     }
 }
 
@@ -249,3 +488,72 @@ impl std::hash::Hash for Expr {
         self.kind.hash(state)
     }
 }
+
+// The `TryFrom` impls below only look at the outermost node: normalize the expression first to
+// extract e.g. the value of `True && False`.
+fn not_a_literal(expected: &'static str, expr: &Expr) -> NotALiteral {
+    NotALiteral::new(expected, expr.to_string())
+}
+
+/// Extracts a `Bool` literal.
+impl std::convert::TryFrom<&Expr> for bool {
+    type Error = NotALiteral;
+    fn try_from(expr: &Expr) -> Result<Self, Self::Error> {
+        match expr.kind() {
+            ExprKind::Num(NumKind::Bool(b)) => Ok(*b),
+            _ => Err(not_a_literal("Bool", expr)),
+        }
+    }
+}
+
+/// Extracts a `Natural` literal.
+impl std::convert::TryFrom<&Expr> for u64 {
+    type Error = NotALiteral;
+    fn try_from(expr: &Expr) -> Result<Self, Self::Error> {
+        match expr.kind() {
+            ExprKind::Num(NumKind::Natural(n)) => match n.to_u64() {
+                Some(n) => Ok(n),
+                None => Err(not_a_literal("64-bit Natural", expr)),
+            },
+            _ => Err(not_a_literal("Natural", expr)),
+        }
+    }
+}
+
+/// Extracts an `Integer` literal.
+impl std::convert::TryFrom<&Expr> for i64 {
+    type Error = NotALiteral;
+    fn try_from(expr: &Expr) -> Result<Self, Self::Error> {
+        match expr.kind() {
+            ExprKind::Num(NumKind::Integer(n)) => match n.to_i64() {
+                Some(n) => Ok(n),
+                None => Err(not_a_literal("64-bit Integer", expr)),
+            },
+            _ => Err(not_a_literal("Integer", expr)),
+        }
+    }
+}
+
+/// Extracts a `Double` literal.
+impl std::convert::TryFrom<&Expr> for f64 {
+    type Error = NotALiteral;
+    fn try_from(expr: &Expr) -> Result<Self, Self::Error> {
+        match expr.kind() {
+            ExprKind::Num(NumKind::Double(n)) => Ok((*n).into()),
+            _ => Err(not_a_literal("Double", expr)),
+        }
+    }
+}
+
+/// Extracts a `Text` literal without interpolations.
+impl std::convert::TryFrom<&Expr> for String {
+    type Error = NotALiteral;
+    fn try_from(expr: &Expr) -> Result<Self, Self::Error> {
+        match expr.kind() {
+            ExprKind::TextLit(t) if t.tail().is_empty() => {
+                Ok(t.head().to_owned())
+            }
+            _ => Err(not_a_literal("Text", expr)),
+        }
+    }
+}
@@ -2,6 +2,7 @@ use crate::syntax::trivial_result;
 
 /// The beginning of a file path which anchors subsequent path components
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FilePrefix {
     /// Absolute path
     Absolute,
@@ -14,12 +15,14 @@ pub enum FilePrefix {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FilePath {
     pub file_path: Vec<String>,
 }
 
 /// The location of import (i.e. local vs. remote vs. environment)
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ImportTarget<SubExpr> {
     Local(FilePrefix, FilePath),
     Remote(URL<SubExpr>),
@@ -28,6 +31,7 @@ pub enum ImportTarget<SubExpr> {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct URL<SubExpr> {
     pub scheme: Scheme,
     pub authority: String,
@@ -37,6 +41,7 @@ pub struct URL<SubExpr> {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Scheme {
     HTTP,
     HTTPS,
@@ -44,6 +49,7 @@ pub enum Scheme {
 
 /// How to interpret the import's contents (i.e. as Dhall code or raw text)
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ImportMode {
     Code,
     RawText,
@@ -51,12 +57,14 @@ pub enum ImportMode {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Hash {
     SHA256(Box<[u8]>),
 }
 
 /// Reference to an external resource
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Import<SubExpr> {
     pub mode: ImportMode,
     pub location: ImportTarget<SubExpr>,
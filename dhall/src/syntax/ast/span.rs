@@ -32,6 +32,26 @@ impl ParsedSpan {
     pub fn to_input(&self) -> String {
         self.input.to_string()
     }
+    /// The source text covered by the span.
+    pub fn as_str(&self) -> &str {
+        &self.input[self.start..self.end]
+    }
+    /// Byte offset of the start of the span in the input.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+    /// Byte offset of the end of the span in the input.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+    /// The whole source text the span points into.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+    /// Whether the two spans point into the same source text.
+    pub fn same_input(&self, other: &ParsedSpan) -> bool {
+        Rc::ptr_eq(&self.input, &other.input)
+    }
     /// Convert to a char range for consumption by annotate_snippets.
     /// This compensates for  https://github.com/rust-lang/annotate-snippets-rs/issues/24
     pub fn as_char_range(&self) -> (usize, usize) {
@@ -1,6 +1,7 @@
 use std::iter::FromIterator;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InterpolatedText<SubExpr> {
     head: String,
     tail: Vec<(SubExpr, String)>,
@@ -27,6 +28,7 @@ impl<SubExpr> From<String> for InterpolatedText<SubExpr> {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum InterpolatedTextContents<SubExpr> {
     Text(String),
     Expr(SubExpr),
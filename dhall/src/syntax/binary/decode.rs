@@ -1,4 +1,5 @@
 use itertools::Itertools;
+use num_bigint::{BigInt, BigUint};
 use std::collections::BTreeMap;
 use std::iter::FromIterator;
 
@@ -31,6 +32,8 @@ pub enum Value {
     Array(Vec<Value>),
     Object(BTreeMap<String, Value>),
     Bytes(Vec<u8>),
+    /// An integer that doesn't fit in 64 bits.
+    Bignum(BigInt),
 }
 
 impl<'b> minicbor::Decode<'b, ()> for Value {
@@ -63,6 +66,7 @@ impl<'b> minicbor::Decode<'b, ()> for Value {
             Type::I16 => Value::I64(d.i16()? as i64),
             Type::I32 => Value::I64(d.i32()? as i64),
             Type::I64 => Value::I64(d.i64()?),
+            Type::Int => Value::Bignum(i128::from(d.int()?).into()),
             Type::F16 => Value::F64(d.f16()? as f64),
             Type::F32 => Value::F64(d.f32()? as f64),
             Type::F64 => Value::F64(d.f64()?),
@@ -86,6 +90,15 @@ impl<'b> minicbor::Decode<'b, ()> for Value {
                 match d.tag()? {
                     // That's the cbor self-description tag.
                     Tag::Unassigned(55799) => Value::decode(d, ctx)?,
+                    Tag::PosBignum => {
+                        let n = BigUint::from_bytes_be(d.bytes()?);
+                        Value::Bignum(n.into())
+                    }
+                    // A negative bignum holds `-1 - n`, like CBOR negative integers.
+                    Tag::NegBignum => {
+                        let n = BigUint::from_bytes_be(d.bytes()?);
+                        Value::Bignum(-1 - BigInt::from(n))
+                    }
                     tag => {
                         throw!("Unknown cbor tag: {tag:?}")
                     }
@@ -93,7 +106,6 @@ impl<'b> minicbor::Decode<'b, ()> for Value {
             }
             t @ (Type::Undefined
             | Type::Simple
-            | Type::Int
             | Type::Break
             | Type::Unknown(_)) => throw!("Unknown cbor type: {t}"),
         })
@@ -312,9 +324,18 @@ fn cbor_value_to_dhall(data: &Value) -> Result<DecodedExpr, DecodeError> {
                 let z = cbor_value_to_dhall(&z)?;
                 Op(BoolIf(x, y, z))
             }
-            [U64(15), U64(x)] => Num(NumKind::Natural(*x as Natural)),
-            [U64(16), U64(x)] => Num(NumKind::Integer(*x as Integer)),
-            [U64(16), I64(x)] => Num(NumKind::Integer(*x as Integer)),
+            [U64(15), U64(x)] => Num(NumKind::Natural(Natural::from(*x))),
+            [U64(15), Bignum(x)] => match x.to_biguint() {
+                Some(x) => Num(NumKind::Natural(x)),
+                None => {
+                    return Err(DecodeError::WrongFormatError(
+                        "negative natural".to_owned(),
+                    ))
+                }
+            },
+            [U64(16), U64(x)] => Num(NumKind::Integer(Integer::from(*x))),
+            [U64(16), I64(x)] => Num(NumKind::Integer(Integer::from(*x))),
+            [U64(16), Bignum(x)] => Num(NumKind::Integer(x.clone())),
             [U64(18), String(first), rest @ ..] => {
                 TextLit(InterpolatedText::from((
                     first.clone(),
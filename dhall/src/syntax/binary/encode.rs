@@ -1,3 +1,6 @@
+use num_bigint::{BigInt, Sign};
+use num_traits::ToPrimitive;
+use std::convert::TryFrom;
 use std::vec;
 
 use crate::builtins::Builtin;
@@ -107,6 +110,31 @@ impl minicbor::Encode<()> for ImportMode {
     }
 }
 
+// An integer, encoded as a CBOR bignum if it doesn't fit in a CBOR integer.
+struct CborInteger(BigInt);
+
+impl minicbor::Encode<()> for CborInteger {
+    fn encode<W: minicbor::encode::Write>(
+        &self,
+        e: &mut minicbor::Encoder<W>,
+        _ctx: &mut (),
+    ) -> Result<(), minicbor::encode::Error<W::Error>> {
+        use minicbor::data::{Int, Tag};
+        let n = &self.0;
+        match n.to_i128().and_then(|n| Int::try_from(n).ok()) {
+            Some(n) => e.int(n)?,
+            // A negative bignum holds `-1 - n`, like CBOR negative integers.
+            None if n.sign() == Sign::Minus => e
+                .tag(Tag::NegBignum)?
+                .bytes(&(-1i32 - n).magnitude().to_bytes_be())?,
+            None => {
+                e.tag(Tag::PosBignum)?.bytes(&n.magnitude().to_bytes_be())?
+            }
+        };
+        Ok(())
+    }
+}
+
 impl minicbor::Encode<()> for Expr {
     fn encode<W: minicbor::encode::Write>(
         &self,
@@ -123,8 +151,12 @@ impl minicbor::Encode<()> for Expr {
             Const(c) => c.to_string().encode(enc, ctx)?,
             Builtin(b) => b.to_string().encode(enc, ctx)?,
             Num(Bool(b)) => b.encode(enc, ctx)?,
-            Num(Natural(n)) => (15u64, n).encode(enc, ctx)?,
-            Num(Integer(n)) => (16u64, n).encode(enc, ctx)?,
+            Num(Natural(n)) => {
+                (15u64, CborInteger(n.clone().into())).encode(enc, ctx)?
+            }
+            Num(Integer(n)) => {
+                (16u64, CborInteger(n.clone())).encode(enc, ctx)?
+            }
             Num(Double(n)) => n.encode(enc, ctx)?,
             Op(BoolIf(x, y, z)) => (14u64, x, y, z).encode(enc, ctx)?,
             Var(V(l, n)) if l.as_ref() == "_" => {
@@ -1,10 +1,13 @@
 use itertools::Itertools;
+use num_traits::{Num, ToPrimitive};
 use pest::prec_climber as pcl;
 use pest::prec_climber::PrecClimber;
 use std::collections::{BTreeMap, BTreeSet};
 use std::iter::once;
+use std::ops::Range;
 use std::rc::Rc;
 
+use pest::error::{ErrorVariant, InputLocation};
 use pest_consume::{match_nodes, Parser};
 
 use crate::operations::OpKind::*;
@@ -25,8 +28,16 @@ type ParsedText = InterpolatedText<Expr>;
 type ParsedTextContents = InterpolatedTextContents<Expr>;
 type ParseInput<'input> = pest_consume::Node<'input, Rule, Rc<str>>;
 
-pub type ParseError = pest::error::Error<Rule>;
-pub type ParseResult<T> = Result<T, ParseError>;
+type ParseResult<T> = Result<T, pest::error::Error<Rule>>;
+
+/// A syntax error. Apart from the message shown by `Display`, it gives access to the location and
+/// cause of the error, e.g. to highlight it in an editor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    err: pest::error::Error<Rule>,
+    expected: Vec<String>,
+    found: Option<String>,
+}
 
 #[derive(Debug)]
 enum Selector {
@@ -376,24 +387,27 @@ impl DhallParser {
         }
     }
 
+    // The grammar only lets through `0`, decimals without leading zeros and lowercase-`0x`
+    // hexadecimals. Naturals are unbounded, so any of these is valid.
     fn natural_literal(input: ParseInput) -> ParseResult<Natural> {
         let s = input.as_str().trim();
         if s.starts_with("0x") {
             let without_prefix = s.trim_start_matches("0x");
-            u64::from_str_radix(without_prefix, 16)
+            Natural::from_str_radix(without_prefix, 16)
                 .map_err(|e| input.error(format!("{}", e)))
         } else {
             s.parse().map_err(|e| input.error(format!("{}", e)))
         }
     }
 
+    // A mandatory sign followed by a `natural_literal`. `+0` and `-0` are both the integer zero.
     fn integer_literal(input: ParseInput) -> ParseResult<Integer> {
         let s = input.as_str().trim();
         let (sign, rest) = (&s[0..1], &s[1..]);
         if rest.starts_with("0x") {
             let without_prefix =
                 sign.to_owned() + rest.trim_start_matches("0x");
-            i64::from_str_radix(&without_prefix, 16)
+            Integer::from_str_radix(&without_prefix, 16)
                 .map_err(|e| input.error(format!("{}", e)))
         } else {
             s.parse().map_err(|e| input.error(format!("{}", e)))
@@ -409,8 +423,13 @@ impl DhallParser {
     }
 
     fn variable(input: ParseInput) -> ParseResult<V> {
-        Ok(match_nodes!(input.into_children();
-            [label(l), natural_literal(idx)] => V(l, idx as usize),
+        Ok(match_nodes!(input.children();
+            [label(l), natural_literal(idx)] => {
+                let idx = idx.to_usize().ok_or_else(|| {
+                    input.error(format!("Variable index {} is too large", idx))
+                })?;
+                V(l, idx)
+            },
             [label(l)] => V(l, 0),
         ))
     }
@@ -1024,18 +1043,75 @@ impl DhallParser {
     }
 }
 
-pub fn parse_expr(input_str: &str) -> ParseResult<Expr> {
-    let rc_input_str = input_str.to_string().into();
-    let inputs = DhallParser::parse_with_userdata(
-        Rule::final_expression,
-        input_str,
-        rc_input_str,
-    )?;
-    Ok(match_nodes!(<DhallParser>; inputs;
-        [expression(e)] => e,
-    ))
+pub fn parse_expr(input_str: &str) -> Result<Expr, ParseError> {
+    let parse = || -> ParseResult<Expr> {
+        let rc_input_str = input_str.to_string().into();
+        let inputs = DhallParser::parse_with_userdata(
+            Rule::final_expression,
+            input_str,
+            rc_input_str,
+        )?;
+        Ok(match_nodes!(<DhallParser>; inputs;
+            [expression(e)] => e,
+        ))
+    };
+    parse().map_err(|err| ParseError::new(err, input_str))
+}
+
+impl ParseError {
+    fn new(err: pest::error::Error<Rule>, input: &str) -> Self {
+        let expected = match &err.variant {
+            ErrorVariant::ParsingError { positives, .. } => {
+                positives.iter().map(|rule| format!("{:?}", rule)).collect()
+            }
+            ErrorVariant::CustomError { .. } => Vec::new(),
+        };
+        let found = match err.location {
+            InputLocation::Pos(pos) => {
+                input[pos..].chars().next().map(String::from)
+            }
+            InputLocation::Span((start, end)) => {
+                Some(input[start..end].to_owned())
+            }
+        };
+        ParseError {
+            err,
+            expected,
+            found,
+        }
+    }
+
+    /// The byte range of the input where the error occurred. It is empty when the parser got
+    /// stuck at a position rather than on a piece of syntax.
+    pub fn span(&self) -> Range<usize> {
+        match self.err.location {
+            InputLocation::Pos(pos) => pos..pos,
+            InputLocation::Span((start, end)) => start..end,
+        }
+    }
+
+    /// The grammar rules that would have been accepted where the parser got stuck, named after
+    /// the rules of `dhall.abnf` with `-` replaced by `_`, e.g. `simple_label`. Empty for errors
+    /// found after parsing, like out-of-range literals.
+    pub fn expected(&self) -> Vec<&str> {
+        self.expected.iter().map(String::as_str).collect()
+    }
+
+    /// The input at the error: the offending character if the parser got stuck, or the offending
+    /// piece of syntax. `None` when the input ended unexpectedly.
+    pub fn found(&self) -> Option<String> {
+        self.found.clone()
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.err)
+    }
 }
 
+impl std::error::Error for ParseError {}
+
 #[test]
 #[cfg_attr(windows, ignore)]
 // Check that the local copy of the grammar file is in sync with the one from dhall-lang.
@@ -1,7 +1,9 @@
 use crate::builtins::Builtin;
+use crate::error::Error;
 use crate::operations::{BinOp, OpKind};
 use crate::syntax::*;
 use itertools::Itertools;
+use num_traits::Signed;
 use std::fmt::{self, Display};
 
 // There is a one-to-one correspondence between the formatter and the grammar. Each phase is
@@ -15,8 +17,9 @@ enum PrintPhase {
     Base,
     // `operator-expression`
     Operator,
-    // All the `<operator>-expression`s
-    BinOp(self::BinOp),
+    // All the `<operator>-expression`s. Operators are left-associative, so the flag is set for a
+    // right operand, where the same operator needs parentheses.
+    BinOp(self::BinOp, bool),
     // `application-expression`
     App,
     // `import-expression`
@@ -25,24 +28,242 @@ enum PrintPhase {
     Primitive,
 }
 
+/// Options that control how an expression is printed as Dhall source.
+///
+/// The `Display` instance of `Expr` prints everything on a single line using the Unicode
+/// operators, with record fields sorted by name; use `Expr::to_string_with` to print with other
+/// options. The latter keeps the fields of record literals and record types in their source
+/// order, when the expression was parsed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PrintOptions {
+    /// Record, union and list literals that would not fit within this many columns are split
+    /// across lines.
+    pub width: usize,
+    /// Number of spaces added for each level of nesting when splitting across lines.
+    pub indent: usize,
+    /// Whether to use the ASCII spelling of binders and operators (`\`, `->`, `forall`, `/\`,
+    /// ...) instead of the Unicode one (`λ`, `→`, `∀`, `∧`, ...).
+    pub ascii_operators: bool,
+    /// Whether to re-emit the comments of the source text. Only the comments before the whole
+    /// expression and around the fields of record literals and record types are kept. This
+    /// relies on the spans of the expression, so comments and field order are lost once the
+    /// expression is transformed (e.g. normalized).
+    pub preserve_comments: bool,
+}
+
+impl PrintOptions {
+    const ONE_LINE: PrintOptions = PrintOptions {
+        width: usize::MAX,
+        indent: 0,
+        ascii_operators: false,
+        preserve_comments: false,
+    };
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        PrintOptions {
+            width: 80,
+            indent: 2,
+            ascii_operators: false,
+            preserve_comments: false,
+        }
+    }
+}
+
+// The printing options along with the current nesting depth.
+#[derive(Copy, Clone)]
+struct Layout {
+    opts: PrintOptions,
+    depth: usize,
+    // Whether to print list literals without spaces inside the brackets, as the `Display`
+    // instance always did, instead of in the style of `dhall format`.
+    compact_lists: bool,
+    // Whether to print the fields of records in their source order, when known, instead of
+    // sorted by label as the `Display` instance always did.
+    source_order: bool,
+}
+
+impl Layout {
+    // Everything on a single line using the Unicode operators, as the `Display` instances do.
+    const ONE_LINE: Layout = Layout {
+        opts: PrintOptions::ONE_LINE,
+        depth: 0,
+        compact_lists: true,
+        source_order: false,
+    };
+
+    fn nested(self) -> Layout {
+        Layout {
+            depth: self.depth + 1,
+            ..self
+        }
+    }
+    // Same options, but without any line splitting.
+    fn flat(self) -> Layout {
+        Layout {
+            opts: PrintOptions {
+                width: usize::MAX,
+                ..self.opts
+            },
+            ..self
+        }
+    }
+    fn fits(self, d: impl Display) -> bool {
+        self.fits_after(0, d)
+    }
+    // Whether `d` fits on the current line after `offset` columns of other text. Printing stops
+    // as soon as the line is full, so this costs at most about `width` columns of output.
+    fn fits_after(self, offset: usize, d: impl Display) -> bool {
+        if self.opts.width == usize::MAX {
+            return true;
+        }
+        let used = self.depth.saturating_mul(self.opts.indent);
+        let used = used.saturating_add(offset);
+        if used > self.opts.width {
+            return false;
+        }
+        let mut w = LineWriter {
+            remaining: self.opts.width - used,
+        };
+        fmt::write(&mut w, format_args!("{}", d)).is_ok()
+    }
+    // Whether `e` fits on the current line after `offset` columns of other text. An expression
+    // is only printed on one line if it prints the same as when nothing is split, so measuring
+    // that is enough; flat printing does no measuring of its own, which keeps this linear.
+    fn fits_flat_after(self, offset: usize, e: PhasedExpr<'_>) -> bool {
+        self.fits_after(offset, PhasedExpr(e.0, e.1, self.flat()))
+    }
+    // Whether `e` fits on the current line. Without an expression to measure, we are printing a
+    // generic `ExprKind`, which always goes on one line.
+    fn fits_expr(self, e: Option<&Expr>) -> bool {
+        match e {
+            Some(e) => {
+                self.fits_flat_after(0, PhasedExpr(e, PrintPhase::Base, self))
+            }
+            None => true,
+        }
+    }
+    fn newline(self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        writeln!(f)?;
+        let indent = self.depth * self.opts.indent;
+        write!(f, "{:1$}", "", indent)
+    }
+    // Picks between the Unicode and the ASCII spelling of a symbol.
+    fn symbol(
+        self,
+        unicode: &'static str,
+        ascii: &'static str,
+    ) -> &'static str {
+        if self.opts.ascii_operators {
+            ascii
+        } else {
+            unicode
+        }
+    }
+}
+
 // Wraps an Expr with a phase, so that phase selection can be done separate from the actual
 // printing.
 #[derive(Copy, Clone)]
-struct PhasedExpr<'a>(&'a Expr, PrintPhase);
+struct PhasedExpr<'a>(&'a Expr, PrintPhase, Layout);
 
 impl<'a> PhasedExpr<'a> {
     fn phase(self, phase: PrintPhase) -> PhasedExpr<'a> {
-        PhasedExpr(self.0, phase)
+        PhasedExpr(self.0, phase, self.2)
+    }
+    fn nested(self) -> PhasedExpr<'a> {
+        PhasedExpr(self.0, self.1, self.2.nested())
+    }
+}
+
+// A subexpression, as printed by `fmt_expr_kind`.
+trait LayoutExpr<'a>: Display + Copy {
+    // The same subexpression, printed one level deeper.
+    fn nested(self) -> Self;
+    // Whether the subexpression fits on the current line after `offset` columns of other text.
+    fn fits_after(self, layout: Layout, offset: usize) -> bool;
+    // The corresponding syntax tree, whose spans locate the source text.
+    fn source(self) -> Option<&'a Expr>;
+}
+
+impl<'a> LayoutExpr<'a> for PhasedExpr<'a> {
+    fn nested(self) -> Self {
+        PhasedExpr::nested(self)
+    }
+    fn fits_after(self, layout: Layout, offset: usize) -> bool {
+        layout.fits_flat_after(offset, self)
+    }
+    fn source(self) -> Option<&'a Expr> {
+        Some(self.0)
+    }
+}
+
+// Wraps a subexpression of a generic `ExprKind`, which is printed on one line with its own
+// `Display` instance.
+struct FlatExpr<'a, SE>(&'a SE);
+
+impl<'a, SE> Clone for FlatExpr<'a, SE> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<'a, SE> Copy for FlatExpr<'a, SE> {}
+
+impl<'a, SE: Display> Display for FlatExpr<'a, SE> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        self.0.fmt(f)
+    }
+}
+
+impl<'a, SE: Display> LayoutExpr<'a> for FlatExpr<'a, SE> {
+    fn nested(self) -> Self {
+        self
+    }
+    fn fits_after(self, _: Layout, _: usize) -> bool {
+        true
+    }
+    fn source(self) -> Option<&'a Expr> {
+        None
+    }
+}
+
+// Displays using the provided closure.
+struct DisplayFn<F>(F);
+
+// Accepts text as long as it fits in the remaining columns of a single line.
+struct LineWriter {
+    remaining: usize,
+}
+
+impl fmt::Write for LineWriter {
+    fn write_str(&mut self, s: &str) -> Result<(), fmt::Error> {
+        for c in s.chars() {
+            if c == '\n' || self.remaining == 0 {
+                return Err(fmt::Error);
+            }
+            self.remaining -= 1;
+        }
+        Ok(())
+    }
+}
+
+impl<F> Display for DisplayFn<F>
+where
+    F: Fn(&mut fmt::Formatter) -> Result<(), fmt::Error>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        (self.0)(f)
     }
 }
 
 impl UnspannedExpr {
     // Annotate subexpressions with the appropriate phase, defaulting to Base
-    fn annotate_with_phases(&self) -> ExprKind<PhasedExpr<'_>> {
+    fn annotate_with_phases(&self, layout: Layout) -> ExprKind<PhasedExpr<'_>> {
         use ExprKind::*;
         use OpKind::*;
         use PrintPhase::*;
-        let with_base = self.map_ref(|e| PhasedExpr(e, Base));
+        let with_base = self.map_ref(|e| PhasedExpr(e, Base, layout));
         match with_base {
             Pi(a, b, c) => {
                 if &String::from(&a) == "_" {
@@ -63,8 +284,8 @@ impl UnspannedExpr {
             Annot(a, b) => Annot(a.phase(Operator), b),
             Op(OpKind::BinOp(op, a, b)) => Op(OpKind::BinOp(
                 op,
-                a.phase(PrintPhase::BinOp(op)),
-                b.phase(PrintPhase::BinOp(op)),
+                a.phase(PrintPhase::BinOp(op, false)),
+                b.phase(PrintPhase::BinOp(op, true)),
             )),
             SomeLit(e) => SomeLit(e.phase(PrintPhase::Import)),
             Op(OpKind::App(f, a)) => Op(OpKind::App(
@@ -85,16 +306,19 @@ impl UnspannedExpr {
             e => e,
         }
     }
+}
 
+impl Expr {
     fn fmt_phase(
         &self,
         f: &mut fmt::Formatter,
         phase: PrintPhase,
+        layout: Layout,
     ) -> Result<(), fmt::Error> {
         use ExprKind::*;
         use OpKind::*;
 
-        let needs_paren = match self {
+        let needs_paren = match self.kind() {
             Lam(_, _, _)
             | Pi(_, _, _)
             | Let(_, _, _, _)
@@ -106,7 +330,7 @@ impl UnspannedExpr {
             | Annot(_, _) => phase > PrintPhase::Base,
             // Precedence is magically handled by the ordering of BinOps. This is reverse Pratt
             // parsing.
-            Op(BinOp(op, _, _)) => phase > PrintPhase::BinOp(*op),
+            Op(BinOp(op, _, _)) => phase > PrintPhase::BinOp(*op, false),
             Op(App(_, _)) => phase > PrintPhase::App,
             Op(Completion(_, _)) => phase > PrintPhase::Import,
             _ => false,
@@ -115,7 +339,8 @@ impl UnspannedExpr {
         if needs_paren {
             f.write_str("(")?;
         }
-        self.annotate_with_phases().fmt(f)?;
+        let kind = self.kind().annotate_with_phases(layout);
+        fmt_expr_kind(&kind, f, layout, Some(self))?;
         if needs_paren {
             f.write_str(")")?;
         }
@@ -146,6 +371,263 @@ where
     f.write_str(close)
 }
 
+// Like `fmt_list`, but splits the list across lines if it doesn't fit within the width or if
+// there are comments to print, in the style of `dhall format`:
+// ```
+// { -- A comment
+//   a = 1
+// , b = 2
+// }
+// ```
+fn fmt_block<T, I, F>(
+    (open, sep, close): (&str, &str, &str),
+    it: I,
+    comments: &BlockComments<'_>,
+    layout: Layout,
+    f: &mut fmt::Formatter,
+    func: F,
+) -> Result<(), fmt::Error>
+where
+    I: IntoIterator<Item = T>,
+    I::IntoIter: Clone,
+    F: Fn(T, Layout, &mut fmt::Formatter) -> Result<(), fmt::Error>,
+{
+    let it = it.into_iter();
+    let multiline = !comments.is_empty()
+        || layout.opts.width != usize::MAX
+            && !layout.fits(DisplayFn(|f: &mut fmt::Formatter| {
+                fmt_list(open, sep, close, it.clone(), f, |x, f| {
+                    func(x, layout.flat(), f)
+                })
+            }));
+    if !multiline {
+        return fmt_list(open, sep, close, it, f, |x, f| func(x, layout, f));
+    }
+
+    // Aligns comments with the entries.
+    let fmt_comment_line = |c: &str, f: &mut fmt::Formatter| {
+        f.write_str(c)?;
+        layout.newline(f)?;
+        f.write_str("  ")
+    };
+    f.write_str(open.trim_end())?;
+    f.write_str(" ")?;
+    for (i, x) in it.enumerate() {
+        if i > 0 {
+            layout.newline(f)?;
+            f.write_str(sep.trim_start())?;
+        }
+        let (leading, trailing) = comments.entry(i);
+        for c in leading {
+            fmt_comment_line(c, f)?;
+        }
+        func(x, layout, f)?;
+        for c in trailing {
+            write!(f, " {}", c)?;
+        }
+    }
+    for c in &comments.closing {
+        layout.newline(f)?;
+        write!(f, "  {}", c)?;
+    }
+    layout.newline(f)?;
+    f.write_str(close.trim_start())
+}
+
+// The comments around the entries of a record, recovered from the source text.
+#[derive(Default)]
+struct BlockComments<'a> {
+    // For each entry, in order, the comments on the lines before it and the ones following it on
+    // the same line.
+    entries: Vec<(Vec<&'a str>, Vec<&'a str>)>,
+    // The comments after the last entry, on their own lines.
+    closing: Vec<&'a str>,
+}
+
+impl<'a> BlockComments<'a> {
+    fn is_empty(&self) -> bool {
+        self.closing.is_empty()
+            && self
+                .entries
+                .iter()
+                .all(|(l, t)| l.is_empty() && t.is_empty())
+    }
+    fn entry(&self, i: usize) -> (&[&'a str], &[&'a str]) {
+        match self.entries.get(i) {
+            Some((l, t)) => (l, t),
+            None => (&[], &[]),
+        }
+    }
+}
+
+// Extracts the comments from a piece of source text that contains no expression, only
+// whitespace, comments, labels and punctuation. Returns each comment along with whether it
+// starts on the first line of the text.
+fn comments_in(text: &str) -> Vec<(bool, &str)> {
+    let bytes = text.as_bytes();
+    let mut comments = Vec::new();
+    let mut first_line = true;
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i..].starts_with(b"--") {
+            let end = text[i..].find('\n').map_or(text.len(), |j| i + j);
+            comments.push((first_line, text[i..end].trim_end()));
+            i = end;
+        } else if bytes[i..].starts_with(b"{-") {
+            // Block comments nest.
+            let mut depth = 0;
+            let mut end = i;
+            while end < bytes.len() {
+                if bytes[end..].starts_with(b"{-") {
+                    depth += 1;
+                    end += 2;
+                } else if bytes[end..].starts_with(b"-}") {
+                    depth -= 1;
+                    end += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    end += 1;
+                }
+            }
+            comments.push((first_line, &text[i..end]));
+            i = end;
+        } else if bytes[i] == b'`' {
+            // Skip quoted labels, which may contain anything.
+            i += text[i + 1..].find('`').map_or(text.len() - i, |j| j + 2);
+        } else if bytes[i].is_ascii_alphabetic() || bytes[i] == b'_' {
+            // Skip unquoted labels as a whole, since they may contain `--`.
+            i += text[i..]
+                .find(|c: char| {
+                    !(c.is_ascii_alphanumeric() || matches!(c, '-' | '/' | '_'))
+                })
+                .unwrap_or(text.len() - i);
+        } else {
+            if bytes[i] == b'\n' {
+                first_line = false;
+            }
+            i += 1;
+        }
+    }
+    comments
+}
+
+impl Layout {
+    // Recovers the order of the `len` entries of the record `expr` in the source text, and the
+    // comments around them if requested. The comments are indexed by position in that order.
+    // Without source information, the entries keep their order, which is sorted by label.
+    fn block_source<'a>(
+        self,
+        expr: Option<&'a Expr>,
+        entries: impl Iterator<Item = Option<&'a Expr>>,
+        len: usize,
+    ) -> (Vec<usize>, BlockComments<'a>) {
+        let unknown = || ((0..len).collect(), BlockComments::default());
+        if !self.source_order {
+            return unknown();
+        }
+        let block = match expr.map(Expr::span_ref) {
+            Some(Span::Parsed(span)) => span,
+            _ => return unknown(),
+        };
+        // Locate the entries in the source.
+        let mut spans = Vec::new();
+        for e in entries {
+            match e.map(Expr::span_ref) {
+                Some(Span::Parsed(span)) if span.same_input(block) => {
+                    spans.push(span)
+                }
+                _ => return unknown(),
+            }
+        }
+        let mut order: Vec<usize> = (0..spans.len()).collect();
+        order.sort_by_key(|&i| spans[i].start());
+        if !self.opts.preserve_comments {
+            return (order, BlockComments::default());
+        }
+
+        let input = block.input();
+        let mut comments = BlockComments {
+            entries: vec![Default::default(); spans.len()],
+            closing: Vec::new(),
+        };
+        // Skip the opening brace.
+        let mut pos = block.start() + 1;
+        for (k, &i) in order.iter().enumerate() {
+            for (first_line, c) in comments_in(&input[pos..spans[i].start()]) {
+                if k > 0 && first_line {
+                    comments.entries[k - 1].1.push(c)
+                } else {
+                    comments.entries[k].0.push(c)
+                }
+            }
+            pos = spans[i].end();
+        }
+        // Skip the closing brace.
+        for (first_line, c) in comments_in(&input[pos..block.end() - 1]) {
+            match comments.entries.last_mut() {
+                Some(last) if first_line => last.1.push(c),
+                _ => comments.closing.push(c),
+            }
+        }
+        (order, comments)
+    }
+}
+
+// Prints a chain of `let`s with one binding per paragraph, in the style of `dhall format`:
+// ```
+// let x = 1
+//
+// let y = 2
+//
+// in  x + y
+// ```
+fn fmt_let_block<'a, E: LayoutExpr<'a>>(
+    e: &ExprKind<E>,
+    layout: Layout,
+    f: &mut fmt::Formatter,
+) -> Result<(), fmt::Error> {
+    if let ExprKind::Let(a, b, c, d) = e {
+        let mut head = format!("let {}", a);
+        if let Some(b) = b {
+            head = format!("{} : {}", head, b);
+        }
+        fmt_nested(&head, " = ", *c, 0, layout, f)?;
+        writeln!(f)?;
+        layout.newline(f)?;
+        match d.source().map(Expr::kind) {
+            Some(d @ ExprKind::Let(..)) => {
+                fmt_let_block(&d.annotate_with_phases(layout), layout, f)?
+            }
+            _ => write!(f, "in  {}", d)?,
+        }
+    }
+    Ok(())
+}
+
+// Prints `head`, `sep` and then `e` one level deeper. If `e` spans several lines or doesn't fit
+// on the line, starts it on a new line so that its lines are all aligned. `offset` is the
+// number of columns already used on the line.
+fn fmt_nested<'a>(
+    head: &str,
+    sep: &str,
+    e: impl LayoutExpr<'a>,
+    offset: usize,
+    layout: Layout,
+    f: &mut fmt::Formatter,
+) -> Result<(), fmt::Error> {
+    f.write_str(head)?;
+    let offset = offset + head.chars().count() + sep.chars().count();
+    if e.fits_after(layout, offset) {
+        f.write_str(sep)?;
+    } else {
+        f.write_str(sep.trim_end())?;
+        layout.nested().newline(f)?;
+    }
+    e.nested().fmt(f)
+}
+
 fn fmt_label(label: &Label, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
     // TODO: distinguish between reserved and nonreserved locations for quoting builtins
     let s = String::from(label);
@@ -165,88 +647,162 @@ fn fmt_label(label: &Label, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
     }
 }
 
-/// Generic instance that delegates to subexpressions
-impl<SE: Display + Clone> Display for ExprKind<SE> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        use crate::syntax::ExprKind::*;
-        match self {
-            Var(a) => a.fmt(f)?,
-            Lam(a, b, c) => {
-                write!(f, "λ(")?;
-                fmt_label(a, f)?;
-                write!(f, " : {}) → {}", b, c)?;
-            }
-            Pi(a, b, c) if &String::from(a) == "_" => {
-                write!(f, "{} → {}", b, c)?;
+// Prints the expression `expr`, whose subexpressions are given by `kind`. The whole
+// expression is needed to measure it and to recover its source text, and is absent when
+// printing a generic `ExprKind` on one line.
+fn fmt_expr_kind<'a, E: LayoutExpr<'a>>(
+    kind: &ExprKind<E>,
+    f: &mut fmt::Formatter,
+    layout: Layout,
+    expr: Option<&'a Expr>,
+) -> Result<(), fmt::Error> {
+    use crate::syntax::ExprKind::*;
+    let arrow = layout.symbol("→", "->");
+    match kind {
+        Var(a) => a.fmt(f)?,
+        Lam(a, b, c) => {
+            f.write_str(layout.symbol("λ(", "\\("))?;
+            fmt_label(a, f)?;
+            write!(f, " : {}) {}", b, arrow)?;
+            if layout.fits_expr(expr) {
+                write!(f, " {}", c)?;
+            } else {
+                layout.nested().newline(f)?;
+                c.nested().fmt(f)?;
             }
-            Pi(a, b, c) => {
-                write!(f, "∀(")?;
-                fmt_label(a, f)?;
-                write!(f, " : {}) → {}", b, c)?;
+        }
+        Pi(a, b, c) if &String::from(a) == "_" => {
+            write!(f, "{} {} {}", b, arrow, c)?;
+        }
+        Pi(a, b, c) => {
+            f.write_str(layout.symbol("∀(", "forall("))?;
+            fmt_label(a, f)?;
+            write!(f, " : {}) {} {}", b, arrow, c)?;
+        }
+        Let(..) if !layout.fits_expr(expr) => fmt_let_block(kind, layout, f)?,
+        Let(a, b, c, d) => {
+            write!(f, "let ")?;
+            fmt_label(a, f)?;
+            if let Some(b) = b {
+                write!(f, " : {}", b)?;
             }
-            Let(a, b, c, d) => {
-                write!(f, "let ")?;
-                fmt_label(a, f)?;
-                if let Some(b) = b {
-                    write!(f, " : {}", b)?;
-                }
-                write!(f, " = {} in {}", c, d)?;
-            }
-            Const(k) => k.fmt(f)?,
-            Builtin(v) => v.fmt(f)?,
-            Num(a) => a.fmt(f)?,
-            TextLit(a) => a.fmt(f)?,
-            SomeLit(e) => {
-                write!(f, "Some {}", e)?;
-            }
-            EmptyListLit(t) => {
-                write!(f, "[] : {}", t)?;
-            }
-            NEListLit(es) => {
-                fmt_list("[", ", ", "]", es, f, Display::fmt)?;
-            }
-            RecordLit(a) if a.is_empty() => f.write_str("{=}")?,
-            RecordLit(a) => fmt_list("{ ", ", ", " }", a, f, |(k, v), f| {
-                fmt_label(k, f)?;
-                write!(f, " = {}", v)
-            })?,
-            RecordType(a) if a.is_empty() => f.write_str("{}")?,
-            RecordType(a) => fmt_list("{ ", ", ", " }", a, f, |(k, t), f| {
-                fmt_label(k, f)?;
-                write!(f, " : {}", t)
-            })?,
-            UnionType(a) => fmt_list("< ", " | ", " >", a, f, |(k, v), f| {
-                fmt_label(k, f)?;
-                if let Some(v) = v {
-                    write!(f, ": {}", v)?;
+            write!(f, " = {} in {}", c, d)?;
+        }
+        Const(k) => k.fmt(f)?,
+        Builtin(v) => v.fmt(f)?,
+        Num(a) => a.fmt(f)?,
+        TextLit(a) => a.fmt(f)?,
+        SomeLit(e) => {
+            write!(f, "Some {}", e)?;
+        }
+        EmptyListLit(t) => {
+            write!(f, "[] : {}", t)?;
+        }
+        NEListLit(es) => {
+            let delimiters = if layout.compact_lists {
+                ("[", ", ", "]")
+            } else {
+                ("[ ", ", ", " ]")
+            };
+            fmt_block(
+                delimiters,
+                es,
+                &BlockComments::default(),
+                layout,
+                f,
+                |e, _, f| e.nested().fmt(f),
+            )?;
+        }
+        RecordLit(a) if a.is_empty() => f.write_str("{=}")?,
+        RecordLit(a) => {
+            let entries: Vec<_> = a.iter().collect();
+            let (order, comments) = layout.block_source(
+                expr,
+                a.values().map(|e| e.source()),
+                a.len(),
+            );
+            fmt_block(
+                ("{ ", ", ", " }"),
+                order.iter().map(|&i| entries[i]),
+                &comments,
+                layout,
+                f,
+                |(k, v), l, f| fmt_nested(&k.to_string(), " = ", *v, 2, l, f),
+            )?
+        }
+        RecordType(a) if a.is_empty() => f.write_str("{}")?,
+        RecordType(a) => {
+            let entries: Vec<_> = a.iter().collect();
+            let (order, comments) = layout.block_source(
+                expr,
+                a.values().map(|e| e.source()),
+                a.len(),
+            );
+            fmt_block(
+                ("{ ", ", ", " }"),
+                order.iter().map(|&i| entries[i]),
+                &comments,
+                layout,
+                f,
+                |(k, t), l, f| fmt_nested(&k.to_string(), " : ", *t, 2, l, f),
+            )?
+        }
+        UnionType(a) => fmt_block(
+            ("< ", " | ", " >"),
+            a,
+            &BlockComments::default(),
+            layout,
+            f,
+            |(k, v), l, f| {
+                let k = k.to_string();
+                match v {
+                    Some(v) => fmt_nested(&k, ": ", *v, 2, l, f),
+                    None => f.write_str(&k),
                 }
-                Ok(())
-            })?,
-            Op(op) => {
-                op.fmt(f)?;
-            }
-            Annot(a, b) => {
-                write!(f, "{} : {}", a, b)?;
-            }
-            Assert(a) => {
-                write!(f, "assert : {}", a)?;
-            }
-            Import(a) => a.fmt(f)?,
+            },
+        )?,
+        Op(op) => {
+            op.fmt_layout(f, layout)?;
         }
-        Ok(())
+        Annot(a, b) => {
+            write!(f, "{} : {}", a, b)?;
+        }
+        Assert(a) => {
+            write!(f, "assert : {}", a)?;
+        }
+        Import(a) => a.fmt(f)?,
     }
+    Ok(())
 }
 
-/// Generic instance that delegates to subexpressions
-impl<SE: Display + Clone> Display for OpKind<SE> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+impl<E: Display> OpKind<E> {
+    fn fmt_layout(
+        &self,
+        f: &mut fmt::Formatter,
+        layout: Layout,
+    ) -> Result<(), fmt::Error> {
         use OpKind::*;
         match self {
             App(a, b) => {
                 write!(f, "{} {}", a, b)?;
             }
             BinOp(op, a, b) => {
-                write!(f, "{} {} {}", a, op, b)?;
+                use crate::operations::BinOp::*;
+                write!(f, "{} ", a)?;
+                match op {
+                    RecursiveRecordMerge => {
+                        f.write_str(layout.symbol("∧", "/\\"))?
+                    }
+                    RecursiveRecordTypeMerge => {
+                        f.write_str(layout.symbol("⩓", "//\\\\"))?
+                    }
+                    RightBiasedRecordMerge => {
+                        f.write_str(layout.symbol("⫽", "//"))?
+                    }
+                    Equivalence => f.write_str(layout.symbol("≡", "==="))?,
+                    _ => op.fmt(f)?,
+                }
+                write!(f, " {}", b)?;
             }
             BoolIf(a, b, c) => {
                 write!(f, "if {} then {} else {}", a, b, c)?;
@@ -286,9 +842,64 @@ impl<SE: Display + Clone> Display for OpKind<SE> {
     }
 }
 
+/// Generic instance that delegates to subexpressions
+impl<SE: Display + Clone> Display for ExprKind<SE> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fmt_expr_kind(&self.map_ref(FlatExpr), f, Layout::ONE_LINE, None)
+    }
+}
+
+/// Generic instance that delegates to subexpressions
+impl<SE: Display + Clone> Display for OpKind<SE> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        self.fmt_layout(f, Layout::ONE_LINE)
+    }
+}
+
+/// Parse Dhall source and print it back in the layout of `dhall format`. Formatting is
+/// idempotent.
+///
+/// Fields of record literals and record types keep their order in the source. Note that the
+/// alternatives of union types are sorted by name, as the syntax tree does not keep track of
+/// their order. Comments are dropped; use `Expr::to_string_with` with
+/// `PrintOptions::preserve_comments` to keep them.
+pub fn format_canonical(src: &str) -> Result<String, Error> {
+    let expr = parse_expr(src)?;
+    let mut out = expr.to_string_with(PrintOptions::default());
+    out.push('\n');
+    Ok(out)
+}
+
+impl Expr {
+    /// Print the expression as Dhall source, according to the given options.
+    pub fn to_string_with(&self, opts: PrintOptions) -> String {
+        let layout = Layout {
+            opts,
+            depth: 0,
+            compact_lists: false,
+            source_order: true,
+        };
+        let mut out = String::new();
+        if let (true, Span::Parsed(span)) =
+            (opts.preserve_comments, self.span_ref())
+        {
+            // Keep the header of the file.
+            for (_, c) in comments_in(&span.input()[..span.start()]) {
+                out.push_str(c);
+                out.push('\n');
+            }
+        }
+        let e = DisplayFn(|f: &mut fmt::Formatter| {
+            self.fmt_phase(f, PrintPhase::Base, layout)
+        });
+        out.push_str(&e.to_string());
+        out
+    }
+}
+
 impl Display for Expr {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        self.kind().fmt_phase(f, PrintPhase::Base)
+        self.fmt_phase(f, PrintPhase::Base, Layout::ONE_LINE)
     }
 }
 
@@ -299,7 +910,7 @@ impl Display for NumKind {
             Bool(true) => f.write_str("True")?,
             Bool(false) => f.write_str("False")?,
             Natural(a) => a.fmt(f)?,
-            Integer(a) if *a >= 0 => {
+            Integer(a) if !a.is_negative() => {
                 f.write_str("+")?;
                 a.fmt(f)?;
             }
@@ -312,7 +923,7 @@ impl Display for NumKind {
 
 impl<'a> Display for PhasedExpr<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        self.0.as_ref().fmt_phase(f, self.1)
+        self.0.fmt_phase(f, self.1, self.2)
     }
 }
 
@@ -398,6 +1009,7 @@ impl Display for NaiveDouble {
         } else if v == 0.0 && v.is_sign_negative() {
             f.write_str("-0.0")
         } else {
+            // This prints the shortest decimal that parses back to the same double.
             let s = format!("{}", v);
             if s.contains('e') || s.contains('.') {
                 f.write_str(&s)
@@ -408,9 +1020,10 @@ impl Display for NaiveDouble {
     }
 }
 
+/// Prints the label in Dhall syntax, quoting it if needed.
 impl Display for Label {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        write!(f, "{}", String::from(self))
+        fmt_label(self, f)
     }
 }
 
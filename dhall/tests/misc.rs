@@ -10,13 +10,15 @@ use dhall::*;
 fn manual_function_application() {
     /// Apply a `Natural -> Natural` function to an argument.
     fn apply_natnat_fn<'cx>(f: &Nir<'cx>, n: u64) -> u64 {
+        use std::convert::TryFrom;
+
         // Convert the number to the internal representation.
-        let n_nir = Nir::from_kind(NirKind::Num(NumKind::Natural(n)));
+        let n_nir = Nir::from_kind(NirKind::Num(NumKind::Natural(n.into())));
         // Apply `f` to `n`.
         let m_nir = f.app(n_nir);
         // Convert from the internal representation.
         match m_nir.kind() {
-            NirKind::Num(NumKind::Natural(m)) => *m,
+            NirKind::Num(NumKind::Natural(m)) => u64::try_from(m).unwrap(),
             _ => panic!("`f` was not `Natural -> Natural`"),
         }
     }
@@ -48,3 +50,1373 @@ fn manual_function_application() {
     // The crate uses essentially a global context, created here.
     Ctxt::with_new(run).unwrap();
 }
+
+#[test]
+fn print_options() {
+    let src = r#"
+        { name = "dhall"
+        , combine = λ(x : { a : Bool }) → x ∧ { b = 1 } ⫽ { c = 2 }
+        , types = ∀(t : Type) → t → { a : t } ⩓ { b : Bool }
+        , check = assert : 1 ≡ 1
+        , tags = ["config", "language", "json", "yaml"]
+        }
+    "#;
+    let expr = Parsed::parse_str(src).unwrap().to_expr();
+
+    // `Display` prints on a single line using Unicode.
+    let one_line = expr.to_string();
+    assert!(!one_line.contains('\n'));
+    assert!(one_line.contains("λ(x : { a : Bool }) →"));
+
+    let ascii = expr.to_string_with(PrintOptions {
+        width: 60,
+        indent: 2,
+        ascii_operators: true,
+        ..PrintOptions::default()
+    });
+    assert_eq!(
+        ascii,
+        r#"{ name = "dhall"
+, combine =
+  \(x : { a : Bool }) -> x /\ { b = 1 } // { c = 2 }
+, types =
+  forall(t : Type) -> t -> { a : t } //\\ { b : Bool }
+, check = assert : 1 === 1
+, tags = [ "config", "language", "json", "yaml" ]
+}"#
+    );
+    assert!(ascii.is_ascii());
+
+    let narrow = expr.to_string_with(PrintOptions {
+        width: 30,
+        ..PrintOptions::default()
+    });
+    assert_eq!(
+        narrow,
+        r#"{ name = "dhall"
+, combine =
+  λ(x : { a : Bool }) →
+    x ∧ { b = 1 } ⫽ { c = 2 }
+, types =
+  ∀(t : Type) → t → { a : t } ⩓ { b : Bool }
+, check = assert : 1 ≡ 1
+, tags =
+  [ "config"
+  , "language"
+  , "json"
+  , "yaml"
+  ]
+}"#
+    );
+
+    // Both forms parse back to the same expression.
+    for s in [one_line, ascii, narrow] {
+        assert_eq!(Parsed::parse_str(&s).unwrap().to_expr(), expr);
+    }
+}
+
+#[test]
+fn shadowed_bindings() {
+    let src = r#"
+        let x = 1
+        let f = λ(x : Natural) → λ(y : Natural) → x + y
+        let _ = assert : f 1 1 ≡ 2
+        let _ = 3
+        let _x = 4
+        let _x = 5
+        in  ∀(y : Type) → Natural → ∀(y : Type) → y
+    "#;
+    let expr = Parsed::parse_str(src).unwrap().to_expr();
+    let shadowed: Vec<_> = expr
+        .shadowed_bindings()
+        .into_iter()
+        .map(|(l, span)| match span {
+            Span::Parsed(span) => (String::from(&l), span.as_str().to_owned()),
+            _ => panic!("expected a parsed span"),
+        })
+        .collect();
+    assert_eq!(
+        shadowed,
+        vec![
+            (
+                "x".to_owned(),
+                "λ(x : Natural) → λ(y : Natural) → x + y".to_owned()
+            ),
+            ("y".to_owned(), "∀(y : Type) → y".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn traverse_imports() {
+    fn run(cx: Ctxt<'_>) -> Result<(), Error> {
+        let expr = Parsed::parse_str("env:PORT + env:OFFSET ? 8080")?.to_expr();
+        let ten = Parsed::parse_str("10")?.to_expr();
+
+        // Resolve environment imports from a fixed map, refusing anything else.
+        let resolved =
+            expr.traverse_imports(&mut |import, _| match &import.location {
+                ImportTarget::Env(var) if var == "PORT" => Ok(ten.clone()),
+                ImportTarget::Env(var) => Err(var.clone()),
+                _ => panic!("unexpected import"),
+            });
+        assert_eq!(resolved.map(|_| ()), Err("OFFSET".to_owned()));
+
+        let resolved = expr
+            .traverse_imports(&mut |_, _| Ok::<_, ()>(ten.clone()))
+            .unwrap();
+        let resolved = Parsed::from_expr_without_imports(resolved)
+            .resolve(cx)?
+            .typecheck(cx)?
+            .normalize(cx);
+        assert_eq!(resolved.to_expr(cx).to_string(), "20");
+        Ok(())
+    }
+    Ctxt::with_new(run).unwrap();
+}
+
+#[test]
+fn with_env_overrides() {
+    use std::collections::HashMap;
+
+    fn run(cx: Ctxt<'_>) -> Result<(), Error> {
+        let src = "env:PORT + (env:DHALL_RUST_TEST_UNSET_VARIABLE ? 1)";
+        let mut overrides = HashMap::new();
+        overrides.insert("PORT".to_owned(), Parsed::parse_str("10")?.to_expr());
+        let parsed = Parsed::parse_str(src)?.with_env_overrides(&overrides);
+        // Only the overridden import is replaced, the other one is left for the resolver.
+        assert_eq!(
+            parsed.to_expr().to_string(),
+            "10 + (env:\"DHALL_RUST_TEST_UNSET_VARIABLE\" ? 1)"
+        );
+        let normalized = parsed.resolve(cx)?.typecheck(cx)?.normalize(cx);
+        assert_eq!(normalized.to_expr(cx).to_string(), "11");
+        Ok(())
+    }
+    Ctxt::with_new(run).unwrap();
+}
+
+#[test]
+#[ignore = "needs a dhall-lang checkout with Prelude"]
+fn prelude_from_local_checkout() {
+    fn run(cx: Ctxt<'_>) -> Result<(), Error> {
+        // Relative imports are resolved from the current directory, i.e. the crate root.
+        let src = r#"
+            let ListP = ../dhall-lang/Prelude/List/package.dhall
+            let TextP = ../dhall-lang/Prelude/Text/package.dhall
+            let JSONP = ../dhall-lang/Prelude/JSON/package.dhall
+            in  { mapped = ListP.map Natural Natural (λ(n : Natural) → n + 1) [ 1, 2 ]
+                , joined = TextP.concatSep ", " [ "a", "b" ]
+                , json = JSONP.render (JSONP.array [ JSONP.bool True, JSONP.null ])
+                }
+        "#;
+        let typed = Parsed::parse_str(src)?.resolve(cx)?.typecheck(cx)?;
+        assert_eq!(
+            typed.get_type()?.to_expr(cx).to_string(),
+            "{ joined : Text, json : Text, mapped : List Natural }"
+        );
+        let normalized = typed.normalize(cx).to_expr(cx);
+        let field =
+            |l: &str| normalized.get_path(&[l.into()]).unwrap().to_string();
+        assert_eq!(field("mapped"), "[2, 3]");
+        assert_eq!(field("joined"), "\"a, b\"");
+        Ok(())
+    }
+    Ctxt::with_new(run).unwrap();
+}
+
+#[test]
+fn print_preserves_comments() {
+    let src = r#"-- Service configuration
+{- Maintained by hand. -}
+{ -- The port to listen on
+  port = 8080
+, host = "localhost" -- no TLS yet
+, limits =
+    { -- Per second
+      rate = 10
+    , burst = 20
+    }
+  -- TODO: more options
+}
+"#;
+    let expr = Parsed::parse_str(src).unwrap().to_expr();
+    let opts = PrintOptions {
+        preserve_comments: true,
+        ..PrintOptions::default()
+    };
+    let printed = expr.to_string_with(opts);
+    assert_eq!(
+        printed,
+        r#"-- Service configuration
+{- Maintained by hand. -}
+{ -- The port to listen on
+  port = 8080
+, host = "localhost" -- no TLS yet
+, limits =
+  { -- Per second
+    rate = 10
+  , burst = 20
+  }
+  -- TODO: more options
+}"#
+    );
+    assert_eq!(Parsed::parse_str(&printed).unwrap().to_expr(), expr);
+    // Printing is stable.
+    let reprinted = Parsed::parse_str(&printed)
+        .unwrap()
+        .to_expr()
+        .to_string_with(opts);
+    assert_eq!(reprinted, printed);
+
+    // Comments are dropped by default, but the fields keep their order.
+    let printed = expr.to_string_with(PrintOptions::default());
+    assert_eq!(
+        printed,
+        r#"{ port = 8080, host = "localhost", limits = { rate = 10, burst = 20 } }"#
+    );
+}
+
+#[test]
+fn print_comments_labels_with_dashes() {
+    // `--` inside a label does not start a comment.
+    let src = "{ a--b = 1, c-- = 2 -- trailing\n}";
+    let expr = Parsed::parse_str(src).unwrap().to_expr();
+    let printed = expr.to_string_with(PrintOptions {
+        preserve_comments: true,
+        ..PrintOptions::default()
+    });
+    assert_eq!(printed, "{ `a--b` = 1\n, `c--` = 2 -- trailing\n}");
+    assert_eq!(Parsed::parse_str(&printed).unwrap().to_expr(), expr);
+}
+
+#[test]
+fn format_canonical() {
+    let src = r#"
+-- Shared settings
+let   base = { replicas = 2,
+   image = "registry.example.com/app:1.0", -- pinned
+   ports = [ 80, 443 ] }   let scale = \(n : Natural) -> base // { replicas = base.replicas * n, image = base.image }
+in scale 3
+"#;
+    let formatted = syntax::format_canonical(src).unwrap();
+    assert_eq!(
+        formatted,
+        r#"let base =
+  { replicas = 2, image = "registry.example.com/app:1.0", ports = [ 80, 443 ] }
+
+let scale =
+  λ(n : Natural) → base ⫽ { replicas = base.replicas * n, image = base.image }
+
+in  scale 3
+"#
+    );
+    assert_eq!(syntax::format_canonical(&formatted).unwrap(), formatted);
+
+    // Union alternatives are sorted by name.
+    assert_eq!(
+        syntax::format_canonical("< B | A : Natural >").unwrap(),
+        "< A: Natural | B >\n"
+    );
+
+    // Short expressions stay on one line.
+    assert_eq!(
+        syntax::format_canonical("let x = 1 in x").unwrap(),
+        "let x = 1 in x\n"
+    );
+}
+
+#[test]
+fn collect_imports() {
+    let src = r#"
+        let a = ./config/a
+        let b = https://example.com/b.dhall using ./headers
+        in  [ a, b, env:C as Text ? missing ]
+    "#;
+    let expr = Parsed::parse_str(src).unwrap().to_expr();
+    let imports: Vec<String> =
+        expr.imports().iter().map(|i| i.to_string()).collect();
+    assert_eq!(
+        imports,
+        vec![
+            "./config/a",
+            "https://example.com/b.dhall using ./headers",
+            "./headers",
+            "env:C as Text",
+            "missing",
+        ]
+    );
+}
+
+#[test]
+fn normalize_without_builtins() {
+    fn run(cx: Ctxt<'_>) -> Result<(), Error> {
+        let src = r#"
+            { even = Natural/even (1 + 1)
+            , sum = Natural/fold 2 Natural (λ(x : Natural) → x + 1) 0
+            , none = None Natural
+            }
+        "#;
+        let typed = Parsed::parse_str(src)?.skip_resolve(cx)?.typecheck(cx)?;
+
+        let opts = NormalizeOptions {
+            reduce_builtins: false,
+        };
+        let kept = typed.normalize_with(cx, opts).to_expr(cx);
+        assert_eq!(
+            kept.to_string(),
+            "{ even = Natural/even 2, none = None Natural, sum = Natural/fold 2 Natural (λ(x : Natural) → x + 1) 0 }"
+        );
+
+        let reduced = typed.normalize(cx).to_expr(cx);
+        assert_eq!(
+            reduced.to_string(),
+            "{ even = True, none = None Natural, sum = 2 }"
+        );
+        Ok(())
+    }
+    Ctxt::with_new(run).unwrap();
+}
+
+#[test]
+fn infer_and_eval() {
+    fn run(cx: Ctxt<'_>) -> Result<(), Error> {
+        let src = "let f = λ(x : Natural) → [ x, x + 1 ] in f 2";
+        let (ty, val) = Parsed::parse_str(src)?
+            .skip_resolve(cx)?
+            .infer_and_eval(cx)?;
+        assert_eq!(ty.to_expr(cx).to_string(), "List Natural");
+        assert_eq!(val.to_expr(cx).to_string(), "[2, 3]");
+        Ok(())
+    }
+    Ctxt::with_new(run).unwrap();
+}
+
+#[test]
+fn typecheck_type() {
+    fn run(cx: Ctxt<'_>) -> Result<(), Error> {
+        let ty = Parsed::parse_str("{ name : Text, tags : List Text }")?
+            .skip_resolve(cx)?
+            .typecheck_type(cx)?;
+        assert_eq!(ty.get_type()?.to_expr(cx).to_string(), "Type");
+
+        let err = Parsed::parse_str("{ name = \"dhall\" }")?
+            .skip_resolve(cx)?
+            .typecheck_type(cx)
+            .unwrap_err();
+        assert!(err.to_string().contains("Expected a type"));
+        Ok(())
+    }
+    Ctxt::with_new(run).unwrap();
+}
+
+/// Generates a random well-scoped expression of type `Natural`, whose free variables are the
+/// `Natural`s in `scope` (innermost last).
+fn random_natural_expr(
+    rng: &mut impl rand::Rng,
+    scope: &mut Vec<&'static str>,
+    depth: usize,
+) -> String {
+    const NAMES: &[&str] = &["x", "y", "_"];
+    let choice = if depth == 0 {
+        rng.gen_range(0..2)
+    } else {
+        rng.gen_range(0..6)
+    };
+    match choice {
+        0 if !scope.is_empty() => {
+            // Pick a variable and compute its de Bruijn index among same-named binders.
+            let pos = rng.gen_range(0..scope.len());
+            let name = scope[pos];
+            let idx = scope[pos + 1..].iter().filter(|n| **n == name).count();
+            format!("{}@{}", name, idx)
+        }
+        0 | 1 => rng.gen_range(0..4).to_string(),
+        2 => format!(
+            "({} + {})",
+            random_natural_expr(rng, scope, depth - 1),
+            random_natural_expr(rng, scope, depth - 1)
+        ),
+        3 => format!(
+            "({} * {})",
+            random_natural_expr(rng, scope, depth - 1),
+            random_natural_expr(rng, scope, depth - 1)
+        ),
+        4 => {
+            let name = NAMES[rng.gen_range(0..NAMES.len())];
+            let val = random_natural_expr(rng, scope, depth - 1);
+            scope.push(name);
+            let body = random_natural_expr(rng, scope, depth - 1);
+            scope.pop();
+            format!("(let {} = {} in {})", name, val, body)
+        }
+        _ => {
+            let name = NAMES[rng.gen_range(0..NAMES.len())];
+            scope.push(name);
+            let body = random_natural_expr(rng, scope, depth - 1);
+            scope.pop();
+            let arg = random_natural_expr(rng, scope, depth - 1);
+            format!("((λ({} : Natural) → {}) {})", name, body, arg)
+        }
+    }
+}
+
+/// Checks variable-handling invariants on random well-scoped expressions. Each expression is
+/// wrapped in binders so that it keeps free variables at the point where it gets evaluated.
+#[test]
+fn random_expr_variable_invariants() {
+    use rand::SeedableRng;
+
+    fn normalize(cx: Ctxt<'_>, src: &str) -> Result<String, Error> {
+        Ok(Parsed::parse_str(src)?
+            .skip_resolve(cx)?
+            .typecheck(cx)?
+            .normalize(cx)
+            .to_expr(cx)
+            .to_string())
+    }
+
+    fn run(cx: Ctxt<'_>) -> Result<(), Error> {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        for _ in 0..50 {
+            let mut scope = vec!["x", "x", "y"];
+            let body = random_natural_expr(&mut rng, &mut scope, 4);
+            let mut inner_scope = vec!["x", "x", "y", "x"];
+            let inner = random_natural_expr(&mut rng, &mut inner_scope, 3);
+            let arg = random_natural_expr(&mut rng, &mut scope, 2);
+            let wrap = |e: String| {
+                format!(
+                    "λ(x : Natural) → λ(x : Natural) → λ(y : Natural) → {}",
+                    e
+                )
+            };
+
+            // Resolving variables and converting back gives the original expression.
+            let src = wrap(body.clone());
+            let parsed = Parsed::parse_str(&src)?;
+            let expected = parsed.to_expr().to_string();
+            let resolved = parsed.skip_resolve(cx)?.to_expr(cx).to_string();
+            assert_eq!(resolved, expected, "in `{}`", src);
+
+            // Normalization is idempotent.
+            let nf = normalize(cx, &src)?;
+            assert_eq!(normalize(cx, &nf)?, nf, "in `{}`", src);
+
+            // Substituting a value for a variable agrees with binding it with `let`.
+            let applied = wrap(format!("(λ(x : Natural) → {}) {}", inner, arg));
+            let let_bound = wrap(format!("let x = {} in {}", arg, inner));
+            assert_eq!(
+                normalize(cx, &applied)?,
+                normalize(cx, &let_bound)?,
+                "in `{}`",
+                applied
+            );
+        }
+        Ok(())
+    }
+    Ctxt::with_new(run).unwrap();
+}
+
+#[test]
+fn shadowing_normal_forms() {
+    // Beta-reduction under binders of the same name must shift the substituted value and the
+    // variables that skip over the removed binder.
+    let cases = [
+        (
+            "λ(a : Natural) → λ(b : Natural) → (λ(x : Natural) → λ(x : Natural) → x@1) a b",
+            "λ(a : Natural) → λ(b : Natural) → a",
+        ),
+        (
+            "λ(a : Natural) → λ(b : Natural) → (λ(x : Natural) → λ(x : Natural) → x) a b",
+            "λ(a : Natural) → λ(b : Natural) → b",
+        ),
+        (
+            "λ(x : Natural) → (λ(y : Natural) → λ(x : Natural) → y) x",
+            "λ(x : Natural) → λ(x : Natural) → x@1",
+        ),
+        (
+            "λ(x : Natural) → (λ(x : Natural) → λ(x : Natural) → x@1) x",
+            "λ(x : Natural) → λ(x : Natural) → x@1",
+        ),
+        (
+            "λ(x : Natural) → λ(x : Natural) → (λ(y : Natural) → y + x@1) x",
+            "λ(x : Natural) → λ(x : Natural) → x + x@1",
+        ),
+        ("λ(x : Natural) → (λ(x : Natural) → x@1) 0", "λ(x : Natural) → x"),
+        (
+            "λ(x : Natural) → (λ(x : Natural) → λ(x : Natural) → x@2) 0 1",
+            "λ(x : Natural) → x",
+        ),
+        (
+            "λ(x : Natural) → λ(y : Natural) → (λ(x : Natural) → λ(y : Natural) → x + y@1) y x",
+            "λ(x : Natural) → λ(y : Natural) → y + y",
+        ),
+        (
+            "λ(x : Natural) → let x = Natural/even x in λ(x : Natural) → x@1",
+            "λ(x : Natural) → λ(x : Natural) → Natural/even x@1",
+        ),
+        (
+            "λ(T : Type) → (λ(T : Type) → λ(x : T@1) → x) Natural",
+            "λ(T : Type) → λ(x : T) → x",
+        ),
+        (
+            "λ(f : Natural → Natural) → λ(x : Natural) → (λ(x : Natural) → f x@1) (f x)",
+            "λ(f : Natural → Natural) → λ(x : Natural) → f x",
+        ),
+        (
+            "λ(x : Natural) → (λ(y : Natural) → λ(x : Natural) → λ(y : Natural) → x@1 + y@1) x",
+            "λ(x : Natural) → λ(x : Natural) → λ(y : Natural) → x@1 + x@1",
+        ),
+    ];
+    Ctxt::with_new(|cx| {
+        for (src, expected) in cases {
+            let nf = Parsed::parse_str(src)?
+                .skip_resolve(cx)?
+                .typecheck(cx)?
+                .normalize(cx)
+                .to_expr(cx)
+                .to_string();
+            assert_eq!(nf, expected, "in `{}`", src);
+        }
+        Ok::<_, Error>(())
+    })
+    .unwrap();
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_roundtrip() {
+    let src = r#"
+        let Config = { name : Text, port : Natural, tags : List Text }
+        let default = { name = "dhall", port = 80, tags = [] : List Text }
+        in  λ(c : Config) →
+              merge
+                { Some = λ(x : Double) → Integer/show -3 ++ "${c.name}"
+                , None = c.name
+                }
+                (Some 1.5)
+              ⫽ { path = ./config/a.dhall sha256:0000000000000000000000000000000000000000000000000000000000000000 ? env:CONFIG as Text }
+              ⫽ default.{ name, port }
+              ⫽ toMap { x = < A | B : Bool >.A, y = x@1 }
+    "#;
+    let expr = Parsed::parse_str(src).unwrap().to_expr();
+    let json = serde_json::to_string(&expr).unwrap();
+    let back: Expr = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, expr);
+}
+
+#[test]
+fn parsed_try_from_str() {
+    use std::convert::TryFrom;
+
+    fn run(cx: Ctxt<'_>) -> Result<(), Error> {
+        let parsed = Parsed::try_from("List/length Bool [ True, False ]")?;
+        let normalized = parsed.skip_resolve(cx)?.typecheck(cx)?.normalize(cx);
+        assert_eq!(normalized.to_expr(cx).to_string(), "2");
+
+        assert!(Parsed::try_from("[ 1,").is_err());
+        Ok(())
+    }
+    Ctxt::with_new(run).unwrap();
+}
+
+#[test]
+fn typecheck_with_import_types() {
+    use std::collections::HashMap;
+
+    fn run(cx: Ctxt<'_>) -> Result<(), Error> {
+        let parse = |s| Parsed::parse_str(s).unwrap().to_expr();
+        let src = r#"
+            let config = ./config
+            let T = ./Type
+            in  { port = config.port + 1
+                , name = env:NAME as Text ? "default"
+                , id = λ(x : T) → x
+                }
+        "#;
+        let parsed = Parsed::parse_str(src)?;
+        let imports: Vec<_> =
+            parsed.to_expr().imports().into_iter().cloned().collect();
+        assert_eq!(imports.len(), 3);
+
+        let mut import_types = HashMap::new();
+        import_types.insert(imports[0].clone(), parse("{ port : Natural }"));
+        import_types.insert(imports[1].clone(), parse("Type"));
+        import_types.insert(imports[2].clone(), parse("Text"));
+        let ty = parsed.typecheck_with_import_types(cx, &import_types)?;
+        assert_eq!(
+            ty.to_string(),
+            "{ id : ∀(x : _import@1) → _import@1, name : Text, port : Natural }"
+        );
+
+        import_types.remove(&imports[0]);
+        let err = parsed
+            .typecheck_with_import_types(cx, &import_types)
+            .unwrap_err();
+        assert!(err.to_string().contains("no type was provided"));
+        Ok(())
+    }
+    Ctxt::with_new(run).unwrap();
+}
+
+#[test]
+fn display_labels() {
+    assert_eq!(Label::from("x").to_string(), "x");
+    assert_eq!(Label::from("a b").to_string(), "`a b`");
+    assert_eq!(Label::from("if").to_string(), "`if`");
+    assert_eq!(V(Label::from("x"), 0).to_string(), "x");
+    assert_eq!(V(Label::from("a b"), 2).to_string(), "`a b`@2");
+
+    let expr = Parsed::parse_str(
+        "λ(r : { `a b` : { c : Natural } }) → r with `a b`.c = 1",
+    )
+    .unwrap()
+    .to_expr();
+    assert_eq!(
+        expr.to_string(),
+        "λ(r : { `a b` : { c : Natural } }) → r with `a b`.c = 1"
+    );
+}
+
+#[test]
+fn substitute_name() {
+    let parse = |s: &str| Parsed::parse_str(s).unwrap().to_expr();
+    let subst = |e: &str, name: &str, v: &str| {
+        parse(e)
+            .substitute_name(&Label::from(name), &parse(v))
+            .to_string()
+    };
+
+    assert_eq!(subst("x + x@1 + y", "x", "1"), "1 + x + y");
+    // Bound occurrences are left alone.
+    assert_eq!(
+        subst("λ(x : Natural) → x + x@1", "x", "1"),
+        "λ(x : Natural) → x + 1"
+    );
+    // Free variables of the value don't get captured.
+    assert_eq!(
+        subst("λ(y : Natural) → x + y", "x", "y"),
+        "λ(y : Natural) → y@1 + y"
+    );
+    assert_eq!(
+        subst("λ(x : Natural) → x@1", "x", "x"),
+        "λ(x : Natural) → x@1"
+    );
+    assert_eq!(
+        subst("let y = 0 in λ(y : Natural) → x", "x", "y@1"),
+        "let y = 0 in λ(y : Natural) → y@3"
+    );
+}
+
+#[test]
+fn generalizable_lets() {
+    fn run(cx: Ctxt<'_>) -> Result<(), Error> {
+        let lint = |s: &str| {
+            Parsed::parse_str(s)
+                .unwrap()
+                .skip_resolve(cx)
+                .unwrap()
+                .generalizable_lets(cx)
+                .unwrap()
+                .into_iter()
+                .map(|(l, _, ty)| format!("{} : {}", l, ty))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            lint("let id = λ(x : Natural) → x in id 1"),
+            ["id : ∀(a : Type) → ∀(x : a) → a"]
+        );
+        assert_eq!(
+            lint(
+                "let pair = λ(x : Text) → λ(n : Natural) → λ(y : Text) → [ x, y ] \
+                 in pair \"a\" 0 \"b\""
+            ),
+            ["pair : ∀(a : Type) → ∀(x : a) → ∀(n : Natural) → ∀(y : a) → List a"]
+        );
+        // The body relies on the argument being a `Natural`.
+        assert!(lint("let succ = λ(x : Natural) → x + 1 in succ 1").is_empty());
+        // Already polymorphic.
+        assert!(lint("let id = λ(T : Type) → λ(x : T) → x in id Natural 1")
+            .is_empty());
+        // Nested bindings see the enclosing binders, and the new type variable avoids existing
+        // names.
+        assert_eq!(
+            lint(
+                "λ(a : Bool) → let k = λ(x : Bool) → λ(y : Natural) → y + 0 in k a 1"
+            ),
+            ["k : ∀(a1 : Type) → ∀(x : a1) → ∀(y : Natural) → Natural"]
+        );
+        // Variables bound outside the function still refer to the same binders.
+        assert_eq!(
+            lint("λ(T : Type) → let f = λ(x : Natural) → λ(y : T) → y in f 0"),
+            ["f : ∀(a : Type) → ∀(x : a) → ∀(y : T) → T"]
+        );
+
+        assert!(Parsed::parse_str("let f = λ(x : Natural) → x + True in f")?
+            .skip_resolve(cx)?
+            .generalizable_lets(cx)
+            .is_err());
+        Ok(())
+    }
+    Ctxt::with_new(run).unwrap();
+}
+
+#[test]
+fn text_literal() {
+    for s in [
+        "",
+        "plain",
+        "\"quoted\"",
+        "back\\slash \\n",
+        "multi\nline\r\n\ttabbed",
+        "${not interpolated} $ {}",
+        "control \u{0} \u{1b} \u{7f}",
+        "unicode λ → 🦀",
+    ] {
+        let expr = Expr::text_literal(s);
+        let printed = expr.to_string();
+        let reparsed = Parsed::parse_str(&printed).unwrap().to_expr();
+        assert_eq!(reparsed, expr, "{}", printed);
+    }
+    assert_eq!(
+        Expr::text_literal("a\"${b}").to_string(),
+        r#""a\"\u0024{b}""#
+    );
+}
+
+#[test]
+fn cata_ana() {
+    let expr = Parsed::parse_str("λ(x : Natural) → let y = x + 1 in [ y, x ]")
+        .unwrap()
+        .to_expr();
+
+    let size = expr.cata(&mut |e: ExprKind<usize>| {
+        let mut size = 1;
+        e.map_ref(|s| size += s);
+        size
+    });
+    assert_eq!(size, 9);
+
+    let depth = expr.cata(&mut |e: ExprKind<usize>| {
+        let mut depth = 0;
+        e.map_ref(|d| depth = depth.max(*d));
+        depth + 1
+    });
+    assert_eq!(depth, 4);
+
+    // Binders show up in the node given to `f`.
+    let binders = expr.cata(&mut |e: ExprKind<Vec<Label>>| {
+        let mut out = Vec::new();
+        if let ExprKind::Lam(l, _, _) | ExprKind::Let(l, _, _, _) = &e {
+            out.push(l.clone());
+        }
+        e.map_ref(|ls| out.extend(ls.iter().cloned()));
+        out
+    });
+    assert_eq!(binders, [Label::from("x"), Label::from("y")]);
+
+    let nested = Expr::ana(3u64, &mut |n| {
+        if n == 0 {
+            ExprKind::Num(NumKind::Natural(0u64.into()))
+        } else {
+            ExprKind::SomeLit(n - 1)
+        }
+    });
+    assert_eq!(nested.to_string(), "Some (Some (Some 0))");
+}
+
+#[test]
+fn double_ordering() {
+    let d = |x: f64| NaiveDouble::from(x);
+
+    // All `NaN`s are equal, and greater than everything else.
+    assert_eq!(d(f64::NAN), d(-f64::NAN));
+    assert_eq!(d(f64::NAN).cmp(&d(-f64::NAN)), std::cmp::Ordering::Equal);
+    assert!(d(f64::INFINITY) < d(f64::NAN));
+    assert!(d(f64::NEG_INFINITY) < d(f64::NAN));
+
+    // Zeroes are distinct, consistently with equality.
+    assert_ne!(d(0.0), d(-0.0));
+    assert!(d(-0.0) < d(0.0));
+    assert!(d(-1.0) < d(-0.0));
+}
+
+#[test]
+fn type_error_trace() {
+    Ctxt::with_new(|cx| {
+        let resolved =
+            Parsed::parse_str(r#"{ x = if True then 1 + "a" else 2 }"#)
+                .unwrap()
+                .skip_resolve(cx)
+                .unwrap();
+
+        // Not recorded by default.
+        let err = resolved.typecheck(cx).unwrap_err();
+        assert!(err.trace().is_empty());
+
+        let err = resolved
+            .typecheck_with_options(
+                cx,
+                TypecheckOptions {
+                    trace: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.trace(),
+            ["RecordLit field \"x\"", "BoolIf then-branch"]
+        );
+        assert!(err.to_string().ends_with(
+            "while typechecking: RecordLit field \"x\" > BoolIf then-branch"
+        ));
+    })
+}
+
+#[test]
+fn semantic_diff() {
+    fn run(cx: Ctxt<'_>) -> Result<(), Error> {
+        let diff = |old: &str, new: &str| -> Result<Vec<String>, Error> {
+            let old = Parsed::parse_str(old)?.to_expr();
+            let new = Parsed::parse_str(new)?.to_expr();
+            let show = |e: Option<Expr>| match e {
+                Some(e) => e.to_string(),
+                None => "-".to_owned(),
+            };
+            Ok(dhall::semantics::semantic_diff(cx, &old, &new)?
+                .into_iter()
+                .map(|c| {
+                    let path: Vec<_> = c
+                        .path
+                        .iter()
+                        .map(|s| match s {
+                            PathSegment::Field(l) => l.to_string(),
+                            PathSegment::Index(i) => i.to_string(),
+                        })
+                        .collect();
+                    format!(
+                        "{}: {} => {}",
+                        path.join("."),
+                        show(c.before),
+                        show(c.after)
+                    )
+                })
+                .collect())
+        };
+
+        // Reordering fields and naming values doesn't change anything.
+        assert!(diff(
+            "{ a = 1, b = [ True ] }",
+            "let t = True in { b = [ t ], a = 0 + 1 }"
+        )?
+        .is_empty());
+
+        assert_eq!(
+            diff(
+                r#"{ a = 1, b = { c = "x", d = [ 1, 2 ] }, e = True }"#,
+                r#"{ e = True, b = { d = [ 1, 2, 3 ], c = "y" }, f = 1 }"#
+            )?,
+            [
+                "a: 1 => -",
+                r#"b.c: "x" => "y""#,
+                "b.d.2: - => 3",
+                "f: - => 1",
+            ]
+        );
+        assert_eq!(diff("[ 1 ]", "[] : List Natural")?, ["0: 1 => -"]);
+
+        assert!(diff("1", "True + 1").is_err());
+        Ok(())
+    }
+    Ctxt::with_new(run).unwrap();
+}
+
+#[test]
+fn is_subrecord() {
+    fn run(cx: Ctxt<'_>) -> Result<(), Error> {
+        let check = |sub: &str, sup: &str| -> Result<bool, Error> {
+            let sub = Parsed::parse_str(sub)?.to_expr();
+            let sup = Parsed::parse_str(sup)?.to_expr();
+            dhall::semantics::is_subrecord(cx, &sub, &sup)
+        };
+
+        assert!(check("{ a : Natural, b : Text }", "{ b : Text }")?);
+        assert!(check("{ a : Natural }", "{}")?);
+        assert!(check("{ a : Natural }", "{ a : Natural }")?);
+        // Field types are compared up to normalization and alpha-equivalence.
+        assert!(check(
+            "{ f : ∀(a : Type) → a → a, n : Natural }",
+            "let N = Natural in { n : N, f : ∀(b : Type) → b → b }"
+        )?);
+
+        assert!(!check("{ b : Text }", "{ a : Natural, b : Text }")?);
+        assert!(!check("{ a : Natural }", "{ a : Integer }")?);
+        assert!(!check("Natural", "{}")?);
+        assert!(check("{ a = 1 }", "{}").is_err());
+        Ok(())
+    }
+    Ctxt::with_new(run).unwrap();
+}
+
+#[test]
+fn compare_shared_values() {
+    // `t40` and `u40` are built separately, and each refers twice to the previous level. Comparing
+    // them naively would visit each of their 2^40 paths.
+    let mut src = String::new();
+    for v in ["t", "u"] {
+        src += &format!("let {}0 = 0 ", v);
+        for i in 1..=40 {
+            src += &format!(
+                "let {v}{i} = {{ a = {v}{j}, b = {v}{j} }} ",
+                v = v,
+                i = i,
+                j = i - 1
+            );
+        }
+    }
+    src += "in assert : t40 === u40";
+
+    Ctxt::with_new(|cx| {
+        Parsed::parse_str(&src)
+            .unwrap()
+            .skip_resolve(cx)
+            .unwrap()
+            .typecheck(cx)
+            .unwrap();
+    })
+}
+
+#[test]
+fn numeric_literals() {
+    let parse = |s: &str| Parsed::parse_str(s).map(|p| p.to_expr());
+    let natural = |n: u64| Some(ExprKind::Num(NumKind::Natural(n.into())));
+    let integer = |n: i64| Some(ExprKind::Num(NumKind::Integer(n.into())));
+    let parse_kind = |s: &str| parse(s).ok().map(|e| e.kind().clone());
+
+    assert_eq!(parse_kind("0"), natural(0));
+    assert_eq!(parse_kind("42"), natural(42));
+    assert_eq!(parse_kind("0x2a"), natural(42));
+    assert_eq!(parse_kind("0x2A"), natural(42));
+    assert_eq!(parse_kind("18446744073709551615"), natural(u64::MAX));
+    assert_eq!(parse_kind("+0"), integer(0));
+    assert_eq!(parse_kind("-0"), integer(0));
+    assert_eq!(parse_kind("+42"), integer(42));
+    assert_eq!(parse_kind("-0x2a"), integer(-42));
+    assert_eq!(parse_kind("-9223372036854775808"), integer(i64::MIN));
+    assert_eq!(parse_kind("-0x8000000000000000"), integer(i64::MIN));
+
+    // Numbers are unbounded.
+    assert_eq!(
+        parse_kind("0x10000000000000000"),
+        parse_kind("18446744073709551616")
+    );
+    for (s, printed) in [
+        ("18446744073709551616", "18446744073709551616"),
+        ("+9223372036854775808", "+9223372036854775808"),
+        ("-0x8000000000000001", "-9223372036854775809"),
+    ] {
+        assert_eq!(parse(s).unwrap().to_string(), printed);
+    }
+
+    // `+0` and `-0` print the same way.
+    assert_eq!(parse("+0").unwrap().to_string(), "+0");
+    assert_eq!(parse("-0").unwrap().to_string(), "+0");
+
+    for s in ["00", "+00", "-00", "042", "0X2a", "0x", "+ 1"] {
+        assert!(parse(s).is_err(), "`{}` should not parse", s);
+    }
+}
+
+#[test]
+fn structural_hash() {
+    Ctxt::with_new(|cx| {
+        let hash = |s: &str| {
+            Parsed::parse_str(s)
+                .unwrap()
+                .skip_resolve(cx)
+                .unwrap()
+                .typecheck(cx)
+                .unwrap()
+                .normalize(cx)
+                .as_nir()
+                .structural_hash(cx)
+        };
+
+        assert_eq!(hash("λ(x : Natural) → x + 0"), hash("λ(y : Natural) → y"));
+        assert_eq!(
+            hash("{ a = 1, b = [ True ] }"),
+            hash("let t = True in { b = [ t ], a = 0 + 1 }")
+        );
+        assert_ne!(hash("1"), hash("2"));
+        assert_ne!(hash("λ(x : Natural) → x"), hash("λ(x : Integer) → x"));
+    })
+}
+
+#[test]
+fn extract_literals() {
+    use std::convert::TryFrom;
+
+    let expr = |s: &str| Parsed::parse_str(s).unwrap().to_expr();
+
+    assert!(bool::try_from(&expr("True")).unwrap());
+    assert_eq!(u64::try_from(&expr("42")).unwrap(), 42);
+    assert_eq!(i64::try_from(&expr("-42")).unwrap(), -42);
+    assert_eq!(f64::try_from(&expr("-1.5")).unwrap(), -1.5);
+    assert!(f64::try_from(&expr("NaN")).unwrap().is_nan());
+    assert_eq!(String::try_from(&expr(r#""a\nb""#)).unwrap(), "a\nb");
+
+    // Only literals of the right type are accepted.
+    assert!(u64::try_from(&expr("+42")).is_err());
+    assert!(i64::try_from(&expr("42")).is_err());
+    assert!(bool::try_from(&expr("True && False")).is_err());
+    assert!(String::try_from(&expr(r#""a${x}b""#)).is_err());
+    assert_eq!(
+        u64::try_from(&expr("18446744073709551616"))
+            .unwrap_err()
+            .to_string(),
+        "expected a 64-bit Natural literal, found `18446744073709551616`"
+    );
+    assert_eq!(
+        u64::try_from(&expr("1 + 1")).unwrap_err().to_string(),
+        "expected a Natural literal, found `1 + 1`"
+    );
+}
+
+#[test]
+fn name_env_indices() {
+    // Innermost binder last.
+    let mut env = NameEnv::new();
+    for name in ["x", "y", "x", "z", "y", "x"] {
+        env.insert_mut(&Label::from(name));
+    }
+    let v = |name: &str, idx| V(Label::from(name), idx);
+
+    for (var, de_bruijn) in [
+        (v("x", 0), 0),
+        (v("y", 0), 1),
+        (v("z", 0), 2),
+        (v("x", 1), 3),
+        (v("y", 1), 4),
+        (v("x", 2), 5),
+    ] {
+        let alpha = env.unlabel_var(&var).unwrap();
+        assert_eq!(alpha.idx(), de_bruijn, "{}", var);
+        assert_eq!(env.label_var(alpha), var);
+    }
+    assert!(env.unlabel_var(&v("x", 3)).is_none());
+    assert!(env.unlabel_var(&v("z", 1)).is_none());
+    assert!(env.unlabel_var(&v("w", 0)).is_none());
+}
+
+#[test]
+fn binary_file_roundtrip() -> Result<(), Error> {
+    // Encoded as specified by the standard, like the reference implementation does.
+    let fixture =
+        include_bytes!("binary-decode/success/regression/RecordOfListA.dhallb");
+    let parsed = Parsed::parse_str("{ a = True, b = [ 1 ] }")?;
+    assert_eq!(parsed.to_binary()?, fixture);
+
+    let parsed = Parsed::parse_str(
+        r#"λ(x : Natural) → { a = x + 1, b = "a${Natural/show x}", c = -2.5 }"#,
+    )?;
+    let path = std::env::temp_dir().join("dhall_binary_file_roundtrip.dhallb");
+    parsed.write_binary_file(&path)?;
+    let read = Parsed::parse_binary_file(&path);
+    std::fs::remove_file(&path)?;
+    assert_eq!(read?.to_expr(), parsed.to_expr());
+    Ok(())
+}
+
+#[test]
+fn binary_big_numbers() -> Result<(), Error> {
+    // Numbers that don't fit in a CBOR integer are encoded as CBOR bignums.
+    for (src, cbor) in [
+        (
+            "18446744073709551616",
+            &[0x82, 0x0f, 0xc2, 0x49, 1, 0, 0, 0, 0, 0, 0, 0, 0][..],
+        ),
+        (
+            "-18446744073709551616",
+            &[
+                0x82, 0x10, 0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                0xff,
+            ],
+        ),
+        (
+            "-18446744073709551617",
+            &[0x82, 0x10, 0xc3, 0x49, 1, 0, 0, 0, 0, 0, 0, 0, 0],
+        ),
+    ] {
+        let parsed = Parsed::parse_str(src)?;
+        assert_eq!(parsed.to_binary()?, cbor, "{}", src);
+        assert_eq!(Parsed::parse_binary(cbor)?.to_expr(), parsed.to_expr());
+    }
+    Ok(())
+}
+
+#[test]
+fn binding_types() {
+    fn run(cx: Ctxt<'_>) -> Result<(), Error> {
+        let types = |s: &str| -> Result<Vec<String>, Error> {
+            Ok(Parsed::parse_str(s)?
+                .skip_resolve(cx)?
+                .binding_types(cx)?
+                .into_iter()
+                .map(|(l, ty)| format!("{} : {}", l, ty))
+                .collect())
+        };
+
+        assert_eq!(
+            types(
+                "let T = Natural
+                 let x : T = 1
+                 let f = λ(y : T) → [ y, x ]
+                 let x = f x
+                 in x"
+            )?,
+            [
+                "T : Type",
+                "x : Natural",
+                "f : ∀(y : Natural) → List Natural",
+                "x : List Natural",
+            ]
+        );
+        assert!(types("1 + 1")?.is_empty());
+        // Only the bindings are typechecked, not the body.
+        assert_eq!(types("let x = 1 in x + True")?, ["x : Natural"]);
+        assert!(types("let x = 1 + True in x").is_err());
+        Ok(())
+    }
+    Ctxt::with_new(run).unwrap();
+}
+
+#[test]
+fn numbered_names() {
+    Ctxt::with_new(|cx| {
+        let normalize = |s: &str| {
+            Parsed::parse_str(s)
+                .unwrap()
+                .skip_resolve(cx)
+                .unwrap()
+                .typecheck(cx)
+                .unwrap()
+                .normalize(cx)
+        };
+        let numbered = ToExprOptions {
+            alpha: true,
+            numbered_names: true,
+        };
+
+        let a = normalize("λ(a : Type) → λ(b : a) → λ(a : Natural) → b");
+        let b = normalize("λ(t : Type) → λ(x : t) → λ(n : Natural) → x");
+        let a_numbered = a.as_nir().to_expr(cx, numbered);
+        assert_eq!(
+            a_numbered.to_string(),
+            "λ(x : Type) → λ(x1 : x) → λ(x2 : Natural) → x1"
+        );
+        // Both modes are canonical.
+        assert_eq!(a_numbered, b.as_nir().to_expr(cx, numbered));
+        assert_eq!(a.to_expr_alpha(cx), b.to_expr_alpha(cx));
+        // And they agree on the meaning of the expression.
+        assert_eq!(
+            normalize(&a_numbered.to_string()).to_expr_alpha(cx),
+            a.to_expr_alpha(cx)
+        );
+    })
+}
+
+#[test]
+fn get_path() {
+    let expr = Parsed::parse_str(
+        "{ a = { b = { c = 1, d = [ True ] } }, e = Some { f = 2 } }",
+    )
+    .unwrap()
+    .to_expr();
+    let get = |path: &[&str]| {
+        let path: Vec<Label> = path.iter().map(|&l| l.into()).collect();
+        expr.get_path(&path).map(|e| e.to_string())
+    };
+
+    assert_eq!(get(&["a", "b", "c"]).as_deref(), Some("1"));
+    assert_eq!(get(&["a", "b", "d"]).as_deref(), Some("[True]"));
+    assert_eq!(get(&["a", "b"]).as_deref(), Some("{ c = 1, d = [True] }"));
+    assert_eq!(get(&[]), Some(expr.to_string()));
+    // Missing fields.
+    assert_eq!(get(&["a", "x"]), None);
+    assert_eq!(get(&["a", "b", "c", "x"]), None);
+    // `Some` is not a record.
+    assert_eq!(get(&["e", "f"]), None);
+}
+
+#[test]
+fn parse_error_location() {
+    let err = parse_expr("{ a = 1, b = }").unwrap_err();
+    assert_eq!(err.span(), 13..13);
+    assert_eq!(err.found().as_deref(), Some("}"));
+    assert!(!err.expected().is_empty());
+
+    let err = parse_expr("[ 1,").unwrap_err();
+    assert_eq!(err.span(), 4..4);
+    assert_eq!(err.found(), None);
+
+    // Out-of-range literals are only caught after parsing.
+    let err = parse_expr("1 + 1e1000").unwrap_err();
+    assert_eq!(err.span(), 4..10);
+    assert_eq!(err.found().as_deref(), Some("1e1000"));
+    assert!(err.expected().is_empty());
+}
+
+#[test]
+fn custom_builtins() {
+    use dhall::builtins::CustomBuiltins;
+    use std::convert::TryFrom;
+
+    let expr = |s: &str| Parsed::parse_str(s).unwrap().to_expr();
+    let mut builtins = CustomBuiltins::new();
+    builtins
+        .insert("MyApp/version", expr("Text"), 0, |_| {
+            Some(Expr::text_literal("1.2.3"))
+        })
+        .unwrap();
+    builtins
+        .insert("MyApp/double", expr("Natural → Natural"), 1, |args| {
+            let n = u64::try_from(&args[0]).ok()?;
+            let kind = ExprKind::Num(NumKind::Natural((2 * n).into()));
+            Some(Expr::new(kind, Span::Artificial))
+        })
+        .unwrap();
+    // Returns an ill-typed result, which must not be used.
+    builtins
+        .insert("MyApp/broken", expr("Natural → Natural"), 1, move |_| {
+            Some(expr("x"))
+        })
+        .unwrap();
+    assert!(builtins
+        .insert("Natural/triple", expr("Natural"), 0, |_| None)
+        .is_err());
+    assert!(builtins
+        .insert("MyApp/bad", expr("Natural/fold"), 0, |_| None)
+        .is_err());
+    assert!(builtins
+        .insert("MyApp/open", expr("x"), 0, |_| None)
+        .is_err());
+
+    Ctxt::with_custom_builtins(builtins, |cx| {
+        let eval = |s: &str| -> Result<String, Error> {
+            let typed =
+                Parsed::parse_str(s)?.skip_resolve(cx)?.typecheck(cx)?;
+            Ok(typed.normalize(cx).to_expr(cx).to_string())
+        };
+        assert_eq!(eval("`MyApp/version`").unwrap(), "\"1.2.3\"");
+        assert_eq!(eval("`MyApp/double` (1 + 2)").unwrap(), "6");
+        // Stuck on a variable.
+        assert_eq!(
+            eval("λ(n : Natural) → `MyApp/double` n").unwrap(),
+            "λ(n : Natural) → `MyApp/double` n"
+        );
+        assert!(eval("`MyApp/double` True").is_err());
+        assert_eq!(eval("`MyApp/broken` 1").unwrap(), "`MyApp/broken` 1");
+        // Binders shadow custom builtins, and other names are still unbound.
+        assert_eq!(
+            eval("λ(`MyApp/version` : Bool) → `MyApp/version`").unwrap(),
+            "λ(`MyApp/version` : Bool) → `MyApp/version`"
+        );
+        assert_eq!(
+            eval("λ(`MyApp/double` : Bool) → `MyApp/double`@1 2").unwrap(),
+            "λ(`MyApp/double` : Bool) → 4"
+        );
+        assert_eq!(
+            eval(
+                "λ(`MyApp/double` : Natural) → `MyApp/double`@1 `MyApp/double`"
+            )
+            .unwrap(),
+            "λ(`MyApp/double` : Natural) → `MyApp/double`@1 `MyApp/double`"
+        );
+        assert!(eval("λ(`MyApp/double` : Bool) → `MyApp/double`@2").is_err());
+        assert!(eval("`MyApp/other`").is_err());
+    });
+}
+
+#[test]
+fn normalize_in_context() {
+    fn run(cx: Ctxt<'_>) -> Result<(), Error> {
+        let expr = |s: &str| Parsed::parse_str(s).unwrap().to_expr();
+        let context: Vec<(Label, ContextItem)> = vec![
+            ("n".into(), ContextItem::Kept(expr("Natural"))),
+            (
+                "double".into(),
+                ContextItem::Replaced(expr("λ(x : Natural) → x + x")),
+            ),
+            ("m".into(), ContextItem::Replaced(expr("double 2"))),
+        ];
+        let parsed =
+            Parsed::parse_str("{ a = double m, b = double n, c = n + m }")?;
+        assert_eq!(
+            parsed.normalize_in_context(cx, &context)?.to_string(),
+            "{ a = 8, b = n + n, c = n + 4 }"
+        );
+        // Other free variables are still unbound.
+        let parsed = Parsed::parse_str("n + k")?;
+        assert!(parsed.normalize_in_context(cx, &context).is_err());
+        Ok(())
+    }
+    Ctxt::with_new(run).unwrap();
+}
+
+#[test]
+fn allow_unused_handlers() {
+    fn run(cx: Ctxt<'_>) -> Result<(), Error> {
+        let src = r#"
+            let Color = < Red | Green >
+            in  merge { Red = 0, Green = 1, Blue = 2 } Color.Green
+        "#;
+        let resolved = Parsed::parse_str(src)?.skip_resolve(cx)?;
+        assert!(resolved.typecheck(cx).is_err());
+
+        let opts = TypecheckOptions {
+            allow_unused_handlers: true,
+            ..Default::default()
+        };
+        let (typed, warnings) = resolved.typecheck_with_warnings(cx, opts)?;
+        assert_eq!(typed.normalize(cx).to_expr(cx).to_string(), "1");
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("unused handler `Blue`"));
+
+        // Missing handlers are still errors.
+        let src = "merge { Red = 0 } < Red | Green >.Green";
+        let resolved = Parsed::parse_str(src)?.skip_resolve(cx)?;
+        assert!(resolved.typecheck_with_warnings(cx, opts).is_err());
+        Ok(())
+    }
+    Ctxt::with_new(run).unwrap();
+}
+
+#[test]
+fn typecheck_collecting() {
+    fn run(cx: Ctxt<'_>) -> Result<(), Error> {
+        let src = r#"
+            { a = 1 + True
+            , b = [ "x" ++ 1, 2 ]
+            , c = merge { X = 1, Y = True } (< X : Natural | Y : Bool >.X 0)
+            , d = 3
+            }
+        "#;
+        let resolved = Parsed::parse_str(src)?.skip_resolve(cx)?;
+        let errors = resolved.typecheck_collecting(cx).unwrap_err();
+        // One for `a`, one for the first element of `b` and one for each handler.
+        assert_eq!(errors.len(), 4);
+        let first = resolved.typecheck(cx).unwrap_err();
+        assert_eq!(errors[0].to_string(), first.to_string());
+
+        // Elements of a list must agree on their type, so only the first mismatch is reported.
+        let src = "[ 1, True, False ]";
+        let resolved = Parsed::parse_str(src)?.skip_resolve(cx)?;
+        assert_eq!(resolved.typecheck_collecting(cx).unwrap_err().len(), 1);
+
+        let resolved = Parsed::parse_str("{ a = 1 }")?.skip_resolve(cx)?;
+        assert!(resolved.typecheck_collecting(cx).is_ok());
+        Ok(())
+    }
+    Ctxt::with_new(run).unwrap();
+}
@@ -1,5 +1,6 @@
 use std::borrow::Cow;
 use std::collections::BTreeMap;
+use std::convert::TryFrom;
 use std::fmt;
 
 use serde::de::value::{
@@ -67,11 +68,11 @@ impl<T> Sealed for T where T: serde::de::DeserializeOwned {}
 /// let mut data = BTreeMap::new();
 /// data.insert(
 ///     "x".to_string(),
-///     serde_dhall::SimpleValue::Num(serde_dhall::NumKind::Natural(1))
+///     serde_dhall::SimpleValue::Num(serde_dhall::NumKind::Natural(1u64.into()))
 /// );
 /// data.insert(
 ///     "y".to_string(),
-///     serde_dhall::SimpleValue::Num(serde_dhall::NumKind::Natural(2))
+///     serde_dhall::SimpleValue::Num(serde_dhall::NumKind::Natural(2u64.into()))
 /// );
 /// let data = serde_dhall::SimpleValue::Record(data);
 ///
@@ -108,6 +109,13 @@ where
 
 struct Deserializer<'a>(Cow<'a, SimpleValue>);
 
+fn too_large(x: impl fmt::Display) -> Error {
+    Error(ErrorKind::Deserialize(format!(
+        "{} is too large to be deserialized: only 64-bit numbers are supported",
+        x
+    )))
+}
+
 impl<'de: 'a, 'a> serde::de::IntoDeserializer<'de, Error> for Deserializer<'a> {
     type Deserializer = Deserializer<'a>;
     fn into_deserializer(self) -> Self::Deserializer {
@@ -128,8 +136,14 @@ impl<'de: 'a, 'a> serde::Deserializer<'de> for Deserializer<'a> {
         let val = |x| Deserializer(Cow::Borrowed(x));
         match self.0.as_ref() {
             Num(Bool(x)) => visitor.visit_bool(*x),
-            Num(Natural(x)) => visitor.visit_u64(*x),
-            Num(Integer(x)) => visitor.visit_i64(*x),
+            Num(Natural(x)) => match u64::try_from(x) {
+                Ok(x) => visitor.visit_u64(x),
+                Err(_) => Err(too_large(x)),
+            },
+            Num(Integer(x)) => match i64::try_from(x) {
+                Ok(x) => visitor.visit_i64(x),
+                Err(_) => Err(too_large(x)),
+            },
             Num(Double(x)) => visitor.visit_f64((*x).into()),
             Text(x) => visitor.visit_str(x),
             List(xs) => {
@@ -201,11 +215,11 @@ impl<'de> serde::de::Visitor<'de> for SimpleValueVisitor {
     }
 
     fn visit_i64<E>(self, value: i64) -> Result<SimpleValue, E> {
-        Ok(SimpleValue::Num(NumKind::Integer(value)))
+        Ok(SimpleValue::Num(NumKind::Integer(value.into())))
     }
 
     fn visit_u64<E>(self, value: u64) -> Result<SimpleValue, E> {
-        Ok(SimpleValue::Num(NumKind::Natural(value)))
+        Ok(SimpleValue::Num(NumKind::Natural(value.into())))
     }
 
     fn visit_f64<E>(self, value: f64) -> Result<SimpleValue, E> {
@@ -1,5 +1,6 @@
 use serde::ser;
 use std::collections::BTreeMap;
+use std::convert::TryFrom;
 
 use dhall::syntax::NumKind;
 
@@ -83,7 +84,7 @@ impl ser::Serializer for Serializer {
         self.serialize_i64(i64::from(v))
     }
     fn serialize_i64(self, v: i64) -> Result<Self::Ok> {
-        Ok(Num(NumKind::Integer(v)))
+        Ok(Num(NumKind::Integer(v.into())))
     }
 
     fn serialize_u8(self, v: u8) -> Result<Self::Ok> {
@@ -96,7 +97,7 @@ impl ser::Serializer for Serializer {
         self.serialize_u64(u64::from(v))
     }
     fn serialize_u64(self, v: u64) -> Result<Self::Ok> {
-        Ok(Num(NumKind::Natural(v)))
+        Ok(Num(NumKind::Natural(v.into())))
     }
 
     fn serialize_f32(self, v: f32) -> Result<Self::Ok> {
@@ -347,14 +348,26 @@ impl serde::ser::Serialize for SimpleValue {
     where
         S: serde::ser::Serializer,
     {
-        use serde::ser::{SerializeMap, SerializeSeq};
+        use serde::ser::{Error, SerializeMap, SerializeSeq};
         use NumKind::*;
         use SimpleValue::*;
 
+        let too_large = |x: &dyn std::fmt::Display| {
+            S::Error::custom(format!(
+                "{} is too large to be serialized: only 64-bit numbers are supported",
+                x
+            ))
+        };
         match self {
             Num(Bool(x)) => serializer.serialize_bool(*x),
-            Num(Natural(x)) => serializer.serialize_u64(*x),
-            Num(Integer(x)) => serializer.serialize_i64(*x),
+            Num(Natural(x)) => match u64::try_from(x) {
+                Ok(x) => serializer.serialize_u64(x),
+                Err(_) => Err(too_large(x)),
+            },
+            Num(Integer(x)) => match i64::try_from(x) {
+                Ok(x) => serializer.serialize_i64(x),
+                Err(_) => Err(too_large(x)),
+            },
             Num(Double(x)) => serializer.serialize_f64((*x).into()),
             Text(x) => serializer.serialize_str(x),
             List(xs) => {
@@ -56,9 +56,9 @@ pub struct Value {
 ///         r.insert(
 ///             "y".to_string(),
 ///             SimpleValue::List(vec![
-///                 SimpleValue::Num(NumKind::Natural(1)),
-///                 SimpleValue::Num(NumKind::Natural(2)),
-///                 SimpleValue::Num(NumKind::Natural(3)),
+///                 SimpleValue::Num(NumKind::Natural(1u64.into())),
+///                 SimpleValue::Num(NumKind::Natural(2u64.into())),
+///                 SimpleValue::Num(NumKind::Natural(3u64.into())),
 ///             ])
 ///         );
 ///         r
@@ -87,8 +87,8 @@ pub struct Value {
 ///     serde_dhall::from_str("{ x = 1, y = 2 }").parse()?;
 ///
 /// let mut map = BTreeMap::new();
-/// map.insert("x".to_string(), SimpleValue::Num(NumKind::Natural(1)));
-/// map.insert("y".to_string(), SimpleValue::Num(NumKind::Natural(2)));
+/// map.insert("x".to_string(), SimpleValue::Num(NumKind::Natural(1u64.into())));
+/// map.insert("y".to_string(), SimpleValue::Num(NumKind::Natural(2u64.into())));
 /// assert_eq!(value, SimpleValue::Record(map));
 /// # Ok(())
 /// # }
@@ -328,7 +328,7 @@ impl SimpleValue {
             }
             NirKind::RecordLit(kvs) => SimpleValue::Record(
                 kvs.iter()
-                    .map(|(k, v)| Ok((k.to_string(), Self::from_nir(v)?)))
+                    .map(|(k, v)| Ok((k.into(), Self::from_nir(v)?)))
                     .collect::<StdResult<_, _>>()?,
             ),
             NirKind::UnionLit(field, x, _) => SimpleValue::Union(
@@ -56,6 +56,9 @@ mod serde {
 
         assert_serde("1.0", 1.0f64);
         assert_serde("1.0", 1.0f32);
+
+        assert!(from_str("18446744073709551616").parse::<u64>().is_err());
+        assert!(from_str("-9223372036854775809").parse::<i64>().is_err());
     }
 
     #[test]